@@ -13,10 +13,7 @@ fn main() {
     #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
     panic!("unsupported target Architecture/OS");
 
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch4")))]
-    panic!("unsupported target Architecture/OS");
-
-    #[cfg(all(target_os = "windows", target_arch = "aarch"))]
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
     panic!("unsupported target Architecture/OS");
 
     let out_dir = env::var_os("OUT_DIR").unwrap();
@@ -25,7 +22,7 @@ fn main() {
     let mut msg = String::with_capacity((exclude_set.len() + include_set.len()) * 12);
 
     // generate EXCLUDE_SET
-    msg.write_str("lazy_static! { static ref EXCLUDE_SET: HashSet<&'static str> = vec![")
+    msg.write_str("lazy_static! { static ref EXCLUDE_SET: HashSet<&'static str> = [")
         .unwrap();
     for term in exclude_set.iter() {
         msg.write_str(format!("\"{}\",", term).as_str()).unwrap();
@@ -33,7 +30,7 @@ fn main() {
     msg.write_str("].iter().copied().collect();}\n").unwrap();
 
     // generate INCLUDE_SET
-    msg.write_str("lazy_static! { static ref INCLUDE_SET: HashSet<&'static str> = vec![")
+    msg.write_str("lazy_static! { static ref INCLUDE_SET: HashSet<&'static str> = [")
         .unwrap();
     for term in include_set.iter() {
         msg.write_str(format!("\"{}\",", term).as_str()).unwrap();
@@ -88,6 +85,11 @@ lazy_static! {
         "rpm",
         "sha256",
         "sha256sum",
+        "sha512",
+        "sha512sum",
+        "sig",
+        "minisig",
+        "sigstore",
 
         // ===============================================
         // OS
@@ -1,17 +1,29 @@
-use clap::{Args, Parser, Subcommand};
+use std::time::Duration;
+
+use clap::{ArgGroup, Args, Parser, Subcommand};
+
+use crate::domain::checksum::ChecksumPolicy;
+use crate::domain::forge::{ForgeKind, ForgeOptions};
+use crate::domain::package::ExtraBin;
+use crate::domain::plain_url::{UrlSource, VersionSource};
+use crate::domain::store::{self, Layout};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 pub struct Cli {
     #[clap(subcommand)]
     pub command: Commands,
+
+    /// don't print errors (see exit codes)
+    #[clap(short, long, global = true)]
+    pub quiet: bool,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// install binaries
     #[clap(arg_required_else_help = true)]
-    Install(InstallArgs),
+    Install(Box<InstallArgs>),
 
     /// update binaries
     Update(UpdateArgs),
@@ -23,25 +35,62 @@ pub enum Commands {
     /// list installed binaries
     List(ListArgs),
 
-    /// match and show info about an available GitHub repo release
+    /// verify installed binaries against their digests recorded at install/update
+    Verify(VerifyArgs),
+
+    /// roll a binary back to a previously installed version, kept in the store
+    #[clap(arg_required_else_help = true)]
+    Rollback(RollbackArgs),
+
+    /// match and show info about an available repo release
     #[clap(arg_required_else_help = true)]
     Info(InfoArgs),
+
+    /// manage the downloaded assets cache
+    #[clap(subcommand)]
+    Cache(CacheCommands),
 }
 
 #[derive(Args, Debug)]
 pub struct InstallArgs {
-    /// GitHub user/repo
+    /// user/repo, optionally prefixed with a forge host (e.g. `gitlab.com/`, `codeberg.org/`)
     #[clap(value_name = "REPO", required = true)]
     pub repo_spec: String,
 
-    /// GitHub API token
-    #[clap(short, long, env = "GITREL_TOKEN")]
-    pub token: Option<String>,
+    /// forge hosting the repo [default: github, or as recognized from REPO]
+    #[clap(long, value_enum, value_name = "FORGE")]
+    pub forge: Option<ForgeKind>,
+
+    #[clap(flatten)]
+    pub tokens: TokenArgs,
+
+    /// API root URL (e.g. GitHub Enterprise `https://HOST/api/v3`)
+    #[clap(long = "api-url", value_name = "URL", env = "GITREL_API_URL")]
+    pub api_url: Option<String>,
+
+    #[clap(flatten)]
+    pub forge_opts: ForgeArgs,
+
+    #[clap(flatten)]
+    pub url_source: UrlSourceArgs,
 
     /// rename binary before installation
     #[clap(short, long = "rename", value_name = "NEW_NAME")]
     pub rename_binary: Option<String>,
 
+    /// also install a binary from an archive ENTRY (file name), optionally renamed to NAME
+    #[clap(short, long = "bin", value_name = "ENTRY[:NAME]")]
+    pub bins: Vec<ExtraBin>,
+
+    /// don't install shell completions and man pages found in an archive asset
+    #[clap(long = "no-side-files")]
+    pub no_side_files: bool,
+
+    /// install binaries built for another OS or architecture than this system's, or of
+    /// an unknown format
+    #[clap(long = "ignore-arch")]
+    pub ignore_arch: bool,
+
     /// override installation path
     #[clap(short, long = "install-path", value_name = "INSTALL_PATH")]
     pub path: Option<String>,
@@ -55,6 +104,44 @@ pub struct InstallArgs {
     #[clap(short, long)]
     pub force: bool,
 
+    /// copy a binary into an install dir, or link to it in the store
+    #[clap(long, value_enum, value_name = "LAYOUT", default_value = "copy")]
+    pub layout: Layout,
+
+    /// number of installed versions of a package to keep, to roll back to
+    #[clap(
+        long,
+        value_name = "N",
+        default_value_t = store::DEFAULT_KEEP,
+        value_parser = clap::value_parser!(u32).range(1..),
+        env = "GITREL_KEEP"
+    )]
+    pub keep: u32,
+
+    /// verify an asset against checksums published with a release
+    #[clap(long, value_enum, value_name = "POLICY", default_value = "warn")]
+    pub checksum: ChecksumPolicy,
+
+    /// verify an asset signature made with a minisign or SSH public KEY (or a file containing it)
+    #[clap(long = "public-key", value_name = "KEY")]
+    pub public_key: Option<String>,
+
+    /// verify a sigstore bundle of an asset (or its checksums) made by a certificate IDENTITY (glob pattern)
+    #[clap(
+        long = "sigstore-identity",
+        value_name = "IDENTITY",
+        requires = "sigstore-issuer"
+    )]
+    pub sigstore_identity: Option<String>,
+
+    /// OIDC issuer of `--sigstore-identity` (e.g. `https://token.actions.githubusercontent.com`)
+    #[clap(
+        long = "sigstore-issuer",
+        value_name = "URL",
+        requires = "sigstore-identity"
+    )]
+    pub sigstore_issuer: Option<String>,
+
     /// match asset name using glob pattern
     #[clap(short = 'a', long = "asset-glob", value_name = "TEXT")]
     pub asset_glob: Option<String>,
@@ -92,9 +179,34 @@ pub struct UpdateArgs {
     /// binary name(s)
     pub bin_names: Vec<String>,
 
-    /// GitHub API token
-    #[clap(short, long, env = "GITREL_TOKEN")]
-    pub token: Option<String>,
+    #[clap(flatten)]
+    pub tokens: TokenArgs,
+
+    /// API root URL (e.g. GitHub Enterprise `https://HOST/api/v3`)
+    #[clap(long = "api-url", value_name = "URL", env = "GITREL_API_URL")]
+    pub api_url: Option<String>,
+
+    #[clap(flatten)]
+    pub forge_opts: ForgeArgs,
+
+    /// number of packages to update concurrently
+    #[clap(
+        short,
+        long,
+        value_name = "N",
+        default_value_t = 4,
+        env = "GITREL_JOBS"
+    )]
+    pub jobs: usize,
+
+    /// number of installed versions of a package to keep, to roll back to
+    /// [default: as installed with]
+    #[clap(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub keep: Option<u32>,
 }
 
 #[derive(Args, Debug)]
@@ -111,15 +223,48 @@ pub struct ListArgs {
     pub wide: bool,
 }
 
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// binary name(s) [default: all, and look for unmanaged files in install dirs]
+    pub bin_names: Vec<String>,
+
+    /// fail on unmanaged files in install dirs too
+    #[clap(long)]
+    pub strict: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct RollbackArgs {
+    /// binary name
+    pub bin_name: String,
+
+    /// release tag of a kept version to roll back to [default: the one installed before]
+    #[clap(long, value_name = "TAG")]
+    pub to: Option<String>,
+}
+
 #[derive(Args, Debug)]
 pub struct InfoArgs {
-    /// GitHub user/repo
+    /// user/repo, optionally prefixed with a forge host (e.g. `gitlab.com/`, `codeberg.org/`)
     #[clap(value_name = "REPO", required = true)]
     pub repo_spec: String,
 
-    /// GitHub API token
-    #[clap(short, long, env = "GITREL_TOKEN")]
-    pub token: Option<String>,
+    /// forge hosting the repo [default: github, or as recognized from REPO]
+    #[clap(long, value_enum, value_name = "FORGE")]
+    pub forge: Option<ForgeKind>,
+
+    #[clap(flatten)]
+    pub tokens: TokenArgs,
+
+    /// API root URL (e.g. GitHub Enterprise `https://HOST/api/v3`)
+    #[clap(long = "api-url", value_name = "URL", env = "GITREL_API_URL")]
+    pub api_url: Option<String>,
+
+    #[clap(flatten)]
+    pub forge_opts: ForgeArgs,
+
+    #[clap(flatten)]
+    pub url_source: UrlSourceArgs,
 
     /// match asset name using glob pattern
     #[clap(short = 'a', long = "asset-glob", value_name = "TEXT")]
@@ -134,3 +279,167 @@ pub struct InfoArgs {
     )]
     pub asset_re: Option<String>,
 }
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommands {
+    /// list cached assets and partial downloads
+    List,
+
+    /// show the total size of the cache
+    Size,
+
+    /// remove cached assets and partial downloads
+    Clean(CacheCleanArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct CacheCleanArgs {
+    /// only remove entries unused for longer than AGE (e.g. `90s`, `12h`, `30d`, `2w`)
+    #[clap(long = "older-than", value_name = "AGE", value_parser = parse_age)]
+    pub older_than: Option<Duration>,
+}
+
+/// Parse an age, such as `30d`, as a number followed by a unit (`s`, `m`, `h`, `d` or `w`).
+fn parse_age(age: &str) -> Result<Duration, String> {
+    let (num, unit) = age.split_at(age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len()));
+    let num: u64 = num
+        .parse()
+        .map_err(|_| format!("`{}` doesn't start with a number", age))?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" | "" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "unknown unit `{}`, expected one of: s, m, h, d, w",
+                unit
+            ))
+        }
+    };
+    num.checked_mul(secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("`{}` is too long an age", age))
+}
+
+#[derive(Args, Debug)]
+pub struct TokenArgs {
+    /// GitHub API token
+    #[clap(short, long, env = "GITREL_TOKEN")]
+    pub token: Option<String>,
+
+    /// GitLab API token
+    #[clap(
+        long = "gitlab-token",
+        value_name = "TOKEN",
+        env = "GITREL_GITLAB_TOKEN"
+    )]
+    pub gitlab_token: Option<String>,
+
+    /// Gitea/Forgejo API token
+    #[clap(long = "gitea-token", value_name = "TOKEN", env = "GITREL_GITEA_TOKEN")]
+    pub gitea_token: Option<String>,
+}
+
+impl TokenArgs {
+    /// A token of a `forge` kind, so that one is never sent to another forge's host.
+    pub fn token_for(&self, forge: ForgeKind) -> Option<&String> {
+        match forge {
+            ForgeKind::GitHub => self.token.as_ref(),
+            ForgeKind::GitLab => self.gitlab_token.as_ref(),
+            ForgeKind::Gitea => self.gitea_token.as_ref(),
+            ForgeKind::Url => None,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ForgeArgs {
+    /// wait for an API rate limit to reset, instead of failing
+    #[clap(long = "wait-rate-limit", env = "GITREL_WAIT_RATE_LIMIT")]
+    pub wait_rate_limit: bool,
+
+    /// report remaining API rate limit quota, and retries
+    #[clap(short, long)]
+    pub verbose: bool,
+
+    /// number of retries of requests failed due to network or server errors
+    #[clap(long, value_name = "N", default_value_t = 3, env = "GITREL_RETRIES")]
+    pub retries: u32,
+
+    /// maximum delay between retries, in seconds
+    #[clap(long = "retry-max-delay", value_name = "SECS", default_value_t = 30)]
+    pub retry_max_delay: u64,
+
+    /// maximum number of release list pages to search for a semver match
+    #[clap(
+        long = "max-pages",
+        value_name = "N",
+        default_value_t = 10,
+        env = "GITREL_MAX_PAGES"
+    )]
+    pub max_pages: usize,
+}
+
+impl ForgeArgs {
+    pub fn forge_options(&self) -> ForgeOptions {
+        ForgeOptions {
+            wait_rate_limit: self.wait_rate_limit,
+            verbose: self.verbose,
+            retries: self.retries,
+            retry_max_delay: Duration::from_secs(self.retry_max_delay),
+            max_pages: self.max_pages,
+            progress: None,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+#[clap(group(ArgGroup::new("versions-source").args(&["versions", "versions-json"])))]
+pub struct UrlSourceArgs {
+    /// download from a URL template instead of a forge, REPO is used as a package name
+    /// (placeholders: {version}, {tag}, {os}, {arch}, {goos}, {goarch})
+    #[clap(
+        long = "url-template",
+        value_name = "URL",
+        requires = "versions-source"
+    )]
+    pub url_template: Option<String>,
+
+    /// available versions of a URL template package
+    #[clap(
+        long,
+        value_name = "VERSION",
+        value_delimiter = ',',
+        requires = "url-template"
+    )]
+    pub versions: Vec<String>,
+
+    /// JSON endpoint listing available versions of a URL template package
+    #[clap(long = "versions-json", value_name = "URL", requires = "url-template")]
+    pub versions_json: Option<String>,
+
+    /// JSON pointer to a version string or an array of them in a `--versions-json` response
+    #[clap(
+        long = "versions-pointer",
+        value_name = "POINTER",
+        requires = "versions-json"
+    )]
+    pub versions_pointer: Option<String>,
+}
+
+impl UrlSourceArgs {
+    pub fn into_url_source(self) -> Option<UrlSource> {
+        let versions = match self.versions_json {
+            Some(url) => VersionSource::Json {
+                url,
+                pointer: self.versions_pointer.unwrap_or_default(),
+            },
+            None => VersionSource::List(self.versions),
+        };
+
+        self.url_template
+            .map(|template| UrlSource { template, versions })
+    }
+}
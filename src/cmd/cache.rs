@@ -0,0 +1,65 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use owo_colors::OwoColorize;
+
+use crate::cli::{CacheCleanArgs, CacheCommands};
+use crate::domain::cache::AssetCache;
+
+/// Manage the downloaded assets cache
+pub fn cache(command: CacheCommands) -> Result<()> {
+    let cache = AssetCache::open()?;
+
+    match command {
+        CacheCommands::List => list(&cache),
+        CacheCommands::Size => size(&cache),
+        CacheCommands::Clean(args) => clean(&cache, args),
+    }
+}
+
+fn list(cache: &AssetCache) -> Result<()> {
+    let entries = cache.entries()?;
+
+    if entries.is_empty() {
+        println!("The cache is empty");
+        return Ok(());
+    }
+
+    for entry in &entries {
+        let last_used: DateTime<Local> = entry.modified.into();
+        println!(
+            "{}  {:>10}  {} [{}]{}",
+            last_used.format("%Y-%m-%d %H:%M"),
+            bytesize::to_string(entry.size, false),
+            entry.name.green(),
+            entry.key.blue(),
+            if entry.partial { " (partial)" } else { "" },
+        );
+    }
+
+    Ok(())
+}
+
+fn size(cache: &AssetCache) -> Result<()> {
+    let entries = cache.entries()?;
+    let size: u64 = entries.iter().map(|entry| entry.size).sum();
+
+    println!(
+        "{} in {} entries",
+        bytesize::to_string(size, false),
+        entries.len()
+    );
+
+    Ok(())
+}
+
+fn clean(cache: &AssetCache, args: CacheCleanArgs) -> Result<()> {
+    let (count, size) = cache.clean(args.older_than)?;
+
+    println!(
+        "removed {} entries, {} freed",
+        count,
+        bytesize::to_string(size, false)
+    );
+
+    Ok(())
+}
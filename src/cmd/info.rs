@@ -1,44 +1,42 @@
 use crate::cli::InfoArgs;
-use crate::domain::github::GitHub;
+use crate::domain::forge::{self, ForgeKind};
 use crate::domain::util;
 use anyhow::Result;
 
 pub async fn info(args: InfoArgs) -> Result<()> {
-    let (user, repo, requested_ver) = util::parse_gh_repo_spec(&args.repo_spec)?;
-    let gh = GitHub::create(args.token.as_ref());
+    let spec = util::parse_gh_repo_spec(&args.repo_spec)?;
+    let url_source = args.url_source.into_url_source();
+    let (forge_kind, api_url) = if url_source.is_some() {
+        (ForgeKind::Url, None)
+    } else {
+        spec.resolve_forge(args.forge, args.api_url.as_deref())?
+    };
+    let gh = forge::create(
+        forge_kind,
+        args.tokens.token_for(forge_kind),
+        api_url.as_deref(),
+        url_source.as_ref(),
+        &args.forge_opts.forge_options(),
+    )?;
 
     let release = gh
         .find_new(
-            &user,
-            &repo,
-            &requested_ver,
+            &spec.user,
+            &spec.repo,
+            &spec.requested,
             args.asset_glob.as_deref(),
             args.asset_re.as_deref(),
         )
-        .await;
+        .await?;
 
-    match release {
-        Ok(release) => {
-            println!("\n         tag: {}", &release.tag_name);
-            println!("published at: {}", &release.published_at);
-            println!("   file name: {}", &release.assets[0].name);
-            println!(
-                "        size: {}",
-                bytesize::to_string(release.assets[0].size, false)
-            );
-            println!("   downloads: {}", release.assets[0].download_count);
+    println!("\n         tag: {}", &release.tag_name);
+    println!("published at: {}", &release.published_at);
+    println!("   file name: {}", &release.assets[0].name);
+    println!(
+        "        size: {}",
+        bytesize::to_string(release.assets[0].size, false)
+    );
+    println!("   downloads: {}", release.assets[0].download_count);
 
-            Ok(())
-        }
-        Err(e) => {
-            use crate::domain::error::GithubError;
-            match e {
-                GithubError::AnyHow(e) => Err(e),
-                e => {
-                    eprint!("\nreason: {}\n\n", e);
-                    Ok(())
-                }
-            }
-        }
-    }
+    Ok(())
 }
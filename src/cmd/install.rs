@@ -1,24 +1,59 @@
+use std::fs;
+use std::io;
 use std::path::PathBuf;
 use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::crate_name;
 use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
 
 use crate::cli::InstallArgs;
-use crate::domain::package::Package;
-use crate::domain::util::{self, message_fail};
-use crate::domain::{executor, installer, package};
-use crate::domain::{github::GitHub, util::packages_file};
+use crate::domain::checksum::{self, Verification};
+use crate::domain::forge::{self, ForgeKind};
+use crate::domain::package::{ExtraBin, Package};
+use crate::domain::signature::{self, PublicKey};
+use crate::domain::sigstore::{self, SigstoreIdentity};
+use crate::domain::store::Layout;
+use crate::domain::util::packages_file;
+use crate::domain::util::{self, message_fail, message_warn};
+use crate::domain::{executor, installer, package, side_files, store};
 
 /// Install packages command
 pub async fn install(args: InstallArgs) -> Result<()> {
     let packages_file = packages_file()?;
     let mut packages_installed = package::read_packages_file(&packages_file)?;
     let temp_dir = tempfile::tempdir().expect("creating a temp dir failed");
-    let gh = GitHub::create(args.token.as_ref());
-    let (user, repo, requested_ver) = util::parse_gh_repo_spec(&args.repo_spec)?;
+    let spec = util::parse_gh_repo_spec(&args.repo_spec)?;
+    let public_key = args
+        .public_key
+        .as_deref()
+        .map(PublicKey::read)
+        .transpose()
+        .context("invalid `--public-key`")?;
+    let sigstore = args
+        .sigstore_identity
+        .zip(args.sigstore_issuer)
+        .map(|(identity, issuer)| SigstoreIdentity { identity, issuer });
+    let url_source = args.url_source.into_url_source();
+    let (forge_kind, api_url) = if url_source.is_some() {
+        (ForgeKind::Url, None)
+    } else {
+        spec.resolve_forge(args.forge, args.api_url.as_deref())?
+    };
+    let util::RepoSpec {
+        user,
+        repo,
+        requested: requested_ver,
+        ..
+    } = spec;
+    let gh = forge::create(
+        forge_kind,
+        args.tokens.token_for(forge_kind),
+        api_url.as_deref(),
+        url_source.as_ref(),
+        &args.forge_opts.forge_options(),
+    )?;
 
     let mut already_installed = None;
     for (i, package) in packages_installed.iter().enumerate() {
@@ -59,19 +94,79 @@ pub async fn install(args: InstallArgs) -> Result<()> {
         .await
     {
         Ok(release) => {
-            let (asset_id, asset_name) = (release.assets[0].id, release.assets[0].name.as_str());
+            let asset_name = release.assets[0].name.as_str();
 
             pb.set_message(format!("downloading {}", style(&repo).green()));
             let asset_path = gh
-                .download(&user, &repo, asset_id, asset_name, &temp_dir)
+                .download(&user, &repo, &release.assets[0], &temp_dir)
                 .await?;
 
+            pb.set_message(format!("verifying {}", style(&repo).green()));
+            let mut verified = match checksum::verify(
+                gh.as_ref(),
+                &user,
+                &repo,
+                &release,
+                &asset_path,
+                &temp_dir,
+                args.checksum,
+            )
+            .await
+            {
+                Ok(Verification::Verified(algorithm)) => format!(", {} verified", algorithm),
+                Ok(Verification::Unverified(reason)) => {
+                    message_warn(&pb, &reason);
+                    String::new()
+                }
+                Ok(Verification::Skipped) => String::new(),
+                Err(e) => {
+                    message_fail(&pb, &repo, "not installed");
+                    return Err(e.into());
+                }
+            };
+
+            if let Some(public_key) = &public_key {
+                if let Err(e) = signature::verify(
+                    gh.as_ref(),
+                    &user,
+                    &repo,
+                    &release,
+                    &asset_path,
+                    &temp_dir,
+                    public_key,
+                )
+                .await
+                {
+                    message_fail(&pb, &repo, "not installed");
+                    return Err(e.into());
+                }
+                verified.push_str(", signature verified");
+            }
+
+            if let Some(signer) = &sigstore {
+                if let Err(e) = sigstore::verify(
+                    gh.as_ref(),
+                    &user,
+                    &repo,
+                    &release,
+                    &asset_path,
+                    &temp_dir,
+                    signer,
+                )
+                .await
+                {
+                    message_fail(&pb, &repo, "not installed");
+                    return Err(e.into());
+                }
+                verified.push_str(", sigstore verified");
+            }
+
             let msg = format!("installing {}", style(&repo).green());
             pb.set_message(msg);
 
             // let bin_dir = util::bin_dir()?;
             let bin_dir = if let Some(p) = &args.path {
-                PathBuf::try_from(p)?
+                PathBuf::from(p)
             } else {
                 util::bin_dir()?
             };
@@ -82,17 +177,24 @@ pub async fn install(args: InstallArgs) -> Result<()> {
                 repo.to_lowercase()
             };
 
+            let store_dir = match args.layout {
+                Layout::Link => Some(store::create_version_dir(&bin_name, &release.tag_name)?),
+                Layout::Copy => None,
+            };
+
             #[cfg(not(target_os = "windows"))]
             let res = installer::install(
                 asset_name,
                 &asset_path,
                 &bin_dir,
                 &bin_name,
+                &args.bins,
+                store_dir.as_deref(),
                 args.strip,
+                args.ignore_arch,
                 args.entry_glob.as_deref(),
                 args.entry_re.as_deref(),
-            )
-            .await;
+            );
 
             #[cfg(target_os = "windows")]
             let res = installer::install(
@@ -100,10 +202,12 @@ pub async fn install(args: InstallArgs) -> Result<()> {
                 &asset_path,
                 &bin_dir,
                 &bin_name,
+                &args.bins,
+                store_dir.as_deref(),
+                args.ignore_arch,
                 args.entry_glob.as_deref(),
                 args.entry_re.as_deref(),
-            )
-            .await;
+            );
 
             match res {
                 Ok(bin_size) => {
@@ -113,25 +217,61 @@ pub async fn install(args: InstallArgs) -> Result<()> {
                         executor::exec(&bin_path, cmd)?;
                     }
 
+                    // as left on disk, after `--exec-after`, for `verify`
+                    let (sha256, size) =
+                        checksum::fingerprint(&util::bin_path(&bin_dir, &bin_name))?;
+                    let mut bins = args.bins;
+                    checksum::fingerprint_bins(&bin_dir, &mut bins)?;
+
+                    let side_files = if args.no_side_files {
+                        Vec::new()
+                    } else {
+                        let bin_names: Vec<_> = std::iter::once(bin_name.as_str())
+                            .chain(bins.iter().map(ExtraBin::bin_name))
+                            .collect();
+                        installer::install_side_files(asset_name, &asset_path, &bin_names)
+                            .unwrap_or_else(|e| {
+                                message_warn(&pb, &format!("side files not installed: {:#}", e));
+                                Vec::new()
+                            })
+                    };
+
                     let msg = format!(
-                        "{} installed {} ({})",
+                        "{} installed {} ({}{})",
                         style('✓').green(),
                         style(&repo).green(),
                         bytesize::to_string(bin_size, false),
+                        verified,
                     );
                     pb.set_style(ProgressStyle::default_bar().template("{msg}").unwrap());
                     pb.finish_with_message(msg);
 
                     #[cfg(not(target_os = "windows"))]
-                    let package = Package {
+                    let mut package = Package {
+                        forge: forge_kind,
                         user,
                         repo,
+                        api_url,
+                        url: url_source,
                         bin_name,
+                        bins,
+                        no_side_files: args.no_side_files.then_some(true),
+                        ignore_arch: args.ignore_arch.then_some(true),
+                        side_files,
                         path: args.path,
                         tag: release.tag_name,
                         requested: requested_ver,
-                        strip: args.strip.then(|| true),
+                        strip: args.strip.then_some(true),
                         timestamp: release.published_at,
+                        sha256: Some(sha256),
+                        size: Some(size),
+                        layout: args.layout,
+                        versions: Vec::new(),
+                        keep: Some(args.keep),
+                        validators: release.validators,
+                        checksum: args.checksum,
+                        public_key,
+                        sigstore,
                         asset_glob: args.asset_glob,
                         asset_re: args.asset_re,
                         entry_glob: args.entry_glob,
@@ -140,20 +280,75 @@ pub async fn install(args: InstallArgs) -> Result<()> {
                     };
 
                     #[cfg(target_os = "windows")]
-                    let package = Package {
+                    let mut package = Package {
+                        forge: forge_kind,
                         user,
                         repo,
+                        api_url,
+                        url: url_source,
                         bin_name,
+                        bins,
+                        no_side_files: args.no_side_files.then_some(true),
+                        ignore_arch: args.ignore_arch.then_some(true),
+                        side_files,
                         path: args.path,
                         tag: release.tag_name,
                         requested: requested_ver,
                         timestamp: release.published_at,
+                        sha256: Some(sha256),
+                        size: Some(size),
+                        layout: args.layout,
+                        versions: Vec::new(),
+                        keep: Some(args.keep),
+                        validators: release.validators,
+                        checksum: args.checksum,
+                        public_key,
+                        sigstore,
                         asset_glob: args.asset_glob,
                         asset_re: args.asset_re,
                         entry_glob: args.entry_glob,
                         entry_re: args.entry_re,
                     };
 
+                    // versions kept before a reinstall can still be rolled back to
+                    if let Some(i) = already_installed {
+                        let replaced = &packages_installed[i];
+                        if let Err(e) =
+                            side_files::remove(&replaced.side_files, &package.side_files)
+                        {
+                            message_warn(&pb, &format!("{:#}", e));
+                        }
+
+                        // as in a rollback, extra binaries not installed anymore are removed
+                        let replaced_bin_dir = match &replaced.path {
+                            Some(p) => PathBuf::from(p),
+                            None => util::bin_dir()?,
+                        };
+                        let installed_names: Vec<_> = std::iter::once(package.bin_name.as_str())
+                            .chain(package.bins.iter().map(ExtraBin::bin_name))
+                            .collect();
+                        for extra_bin in &replaced.bins {
+                            if installed_names.contains(&extra_bin.bin_name()) {
+                                continue;
+                            }
+                            let bin_path = util::bin_path(&replaced_bin_dir, extra_bin.bin_name());
+                            match fs::remove_file(&bin_path) {
+                                Err(e) if e.kind() != io::ErrorKind::NotFound => message_warn(
+                                    &pb,
+                                    &format!("deleting a binary: {}: {}", bin_path.display(), e),
+                                ),
+                                _ => (),
+                            }
+                        }
+
+                        if replaced.bin_name == package.bin_name {
+                            package.versions = std::mem::take(&mut packages_installed[i].versions);
+                        }
+                    }
+                    if let Err(e) = store::keep(&mut package, &bin_dir) {
+                        message_warn(&pb, &format!("not kept to roll back to: {:#}", e));
+                    }
+
                     if let Some(i) = already_installed {
                         packages_installed[i] = package;
                     } else {
@@ -169,29 +364,13 @@ pub async fn install(args: InstallArgs) -> Result<()> {
                 }
                 Err(e) => {
                     message_fail(&pb, &repo, "not installed");
-
-                    use crate::domain::error::InstallerError;
-                    match e {
-                        InstallerError::AnyHow(e) => Err(e),
-                        e => {
-                            eprint!("\nreason: {}\n\n", e);
-                            Ok(())
-                        }
-                    }
+                    Err(e.into())
                 }
             }
         }
         Err(e) => {
             message_fail(&pb, &repo, "not installed");
-
-            use crate::domain::error::GithubError;
-            match e {
-                GithubError::AnyHow(e) => Err(e),
-                e => {
-                    eprint!("\nreason: {}\n\n", e);
-                    Ok(())
-                }
-            }
+            Err(e.into())
         }
     }
 }
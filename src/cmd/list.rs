@@ -19,7 +19,7 @@ use crate::{
 #[derive(Tabled)]
 struct ListLine<'a> {
     #[tabled(rename = "Bin")]
-    bin: &'a str,
+    bin: String,
     #[tabled(rename = "Requested")]
     requested: &'a str,
     #[tabled(rename = "Installed")]
@@ -49,10 +49,22 @@ pub fn list(args: ListArgs) -> Result<()> {
 
     for pkg in &packages_installed {
         list_lines.push(ListLine {
-            bin: &pkg.bin_name,
+            // along with more binaries installed from the same asset
+            bin: std::iter::once(pkg.bin_name.as_str())
+                .chain(pkg.bins.iter().map(|extra_bin| extra_bin.bin_name()))
+                .collect::<Vec<_>>()
+                .join(", "),
             requested: &pkg.requested,
             installed: &pkg.tag,
-            repository: format!("https://github.com/{}/{}", &pkg.user, &pkg.repo),
+            repository: match &pkg.url {
+                Some(url_source) => url_source.template.clone(),
+                None => format!(
+                    "{}/{}/{}",
+                    pkg.forge.web_url(pkg.api_url.as_deref()),
+                    &pkg.user,
+                    &pkg.repo
+                ),
+            },
             path: pkg.path.as_ref().map_or(default_bin_path, |p| p.as_str()),
         });
     }
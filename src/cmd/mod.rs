@@ -1,11 +1,17 @@
+mod cache;
 mod info;
 mod install;
 mod list;
+mod rollback;
 mod uninstall;
 mod update;
+mod verify;
 
+pub use self::cache::cache;
 pub use self::info::info;
 pub use self::install::install;
 pub use self::list::list;
+pub use self::rollback::rollback;
 pub use self::uninstall::uninstall;
 pub use self::update::update;
+pub use self::verify::verify;
@@ -0,0 +1,112 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use clap::crate_name;
+use console::style;
+
+use crate::cli::RollbackArgs;
+use crate::domain::util::{self, packages_file};
+use crate::domain::{installer, package, side_files, store};
+
+/// Roll a package back to a kept version, without network access
+pub fn rollback(args: RollbackArgs) -> Result<()> {
+    let packages_file = packages_file()?;
+    let mut packages_installed = package::read_packages_file(&packages_file)?;
+
+    let pkg = packages_installed
+        .iter_mut()
+        .find(|pkg| pkg.bin_name == args.bin_name)
+        .ok_or_else(|| anyhow!("package `{}` is not installed", args.bin_name))?;
+
+    let kept_tags = || {
+        pkg.versions
+            .iter()
+            .map(|version| version.tag.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let version = store::rollback_target(&pkg.versions, &pkg.tag, args.to.as_deref())
+        .ok_or_else(|| match &args.to {
+            Some(tag) => anyhow!(
+                "version `{}` of `{}` is not kept, kept versions: {}",
+                tag,
+                pkg.bin_name,
+                kept_tags()
+            ),
+            None => anyhow!(
+                "no version of `{}` installed before {} is kept, use `--to TAG` to pick one of: {}",
+                pkg.bin_name,
+                pkg.tag,
+                kept_tags()
+            ),
+        })?
+        .clone();
+
+    if version.tag == pkg.tag {
+        println!("{} is already at {}", &pkg.bin_name, &pkg.tag);
+        return Ok(());
+    }
+
+    let bin_dir = match &pkg.path {
+        Some(p) => PathBuf::from(p),
+        None => util::bin_dir()?,
+    };
+    let stored_path = store::stored_path(pkg, &version.tag, &pkg.bin_name)?;
+    let bin_size = installer::restore(&stored_path, &bin_dir, &pkg.bin_name, pkg.layout)?;
+
+    // more binaries installed along with the main one, as of a kept version
+    for extra_bin in &version.bins {
+        let stored_path = store::stored_path(pkg, &version.tag, extra_bin.bin_name())?;
+        installer::restore(&stored_path, &bin_dir, extra_bin.bin_name(), pkg.layout)?;
+    }
+    for extra_bin in &pkg.bins {
+        if !version
+            .bins
+            .iter()
+            .any(|kept| kept.bin_name() == extra_bin.bin_name())
+        {
+            let bin_path = util::bin_path(&bin_dir, extra_bin.bin_name());
+            match fs::remove_file(&bin_path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    return Err(e)
+                        .with_context(|| format!("deleting a binary: {}", bin_path.display()))
+                }
+                _ => (),
+            }
+        }
+    }
+    // the kept version is in place by now, so it's recorded regardless
+    if let Err(e) = side_files::remove(&pkg.side_files, &version.side_files) {
+        eprintln!("{} {:#}", style("warning:").yellow(), e);
+    }
+
+    println!(
+        "{} rolled back {} {} -> {} ({})",
+        style('✓').green(),
+        style(&pkg.bin_name).green(),
+        &pkg.tag,
+        &version.tag,
+        bytesize::to_string(bin_size, false),
+    );
+    println!(
+        "note: `{} update` will update it again, unless it's reinstalled as `{}/{}@{}`",
+        crate_name!(),
+        &pkg.user,
+        &pkg.repo,
+        &version.tag
+    );
+
+    pkg.tag = version.tag;
+    pkg.timestamp = version.timestamp;
+    pkg.sha256 = Some(version.sha256);
+    pkg.size = Some(version.size);
+    pkg.bins = version.bins;
+    pkg.side_files = version.side_files;
+    // a conditional request would otherwise tell the rolled back version is up to date
+    pkg.validators = None;
+
+    package::write_packages_file(&packages_file, &packages_installed)?;
+    Ok(())
+}
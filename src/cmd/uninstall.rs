@@ -1,7 +1,9 @@
 use std::collections::HashSet;
+use std::fs;
+use std::io;
 use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::crate_name;
 use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
@@ -9,7 +11,8 @@ use indicatif::{ProgressBar, ProgressStyle};
 use crate::cli::UninstallArgs;
 use crate::domain::package::{self, write_packages_file};
 use crate::domain::uninstaller::uninstall as uninstall_binary;
-use crate::domain::util::{bin_dir, message_fail, packages_file};
+use crate::domain::util::{self, bin_dir, message_fail, packages_file};
+use crate::domain::{side_files, store};
 
 /// Uninstall installed packages
 pub async fn uninstall(args: UninstallArgs) -> Result<()> {
@@ -59,7 +62,7 @@ pub async fn uninstall(args: UninstallArgs) -> Result<()> {
         // either use the default path or the one specified in a package spec
         let pkg_path;
         let bin_dir = if let Some(p) = &pkg.path {
-            pkg_path = std::path::PathBuf::try_from(p)?;
+            pkg_path = std::path::PathBuf::from(p);
             pkg_path.as_path()
         } else {
             default_bin_dir.as_path()
@@ -74,7 +77,22 @@ pub async fn uninstall(args: UninstallArgs) -> Result<()> {
             }
         }
 
-        match uninstall_binary(bin_name, bin_dir) {
+        let res = uninstall_binary(bin_name, bin_dir)
+            .and_then(|()| {
+                // more binaries installed along with the main one
+                pkg.bins.iter().try_for_each(|extra_bin| {
+                    let bin_path = util::bin_path(bin_dir, extra_bin.bin_name());
+                    match fs::remove_file(&bin_path) {
+                        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e)
+                            .with_context(|| format!("deleting a binary: {}", bin_path.display())),
+                        _ => Ok(()),
+                    }
+                })
+            })
+            .and_then(|()| side_files::remove(&pkg.side_files, &[]))
+            .and_then(|()| store::remove(&pkg.bin_name));
+
+        match res {
             Ok(()) => {
                 let msg = format!(
                     "{} uninstalled {}",
@@ -1,17 +1,56 @@
 use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use anyhow::{Context, Result};
 use clap::crate_name;
 use console::style;
-use indicatif::{ProgressBar, ProgressStyle};
+use futures_util::{stream, StreamExt};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use owo_colors::OwoColorize;
+use tabled::{object::Columns, style::Style, ModifyObject, Table, Tabled};
 
 use crate::cli::UpdateArgs;
-use crate::domain::error::GithubError;
-use crate::domain::github::GitHub;
+use crate::domain::checksum::{self, Verification};
+use crate::domain::error::{GithubError, InstallerError, UpdateFailed};
+use crate::domain::forge::{self, ForgeKind, ForgeOptions, Validators};
+use crate::domain::github::Release;
+use crate::domain::package::{ExtraBin, Package};
+use crate::domain::store::Layout;
 use crate::domain::util::packages_file;
 use crate::domain::{executor, package};
 use crate::domain::{installer, util};
+use crate::domain::{side_files, signature, sigstore, store};
+
+/// An outcome of updating a single package.
+enum Outcome {
+    /// updated to a `release`, with a binary of a given size and SHA-256 digest
+    Updated {
+        release: Box<Release>,
+        bin_size: u64,
+        sha256: String,
+        /// more binaries installed along with the main one
+        bins: Vec<ExtraBin>,
+        /// shell completions and man pages installed along with binaries
+        side_files: Vec<PathBuf>,
+    },
+    /// already up to date, with validators to record (see `GithubError::AlreadyUpToDate`)
+    UpToDate(Option<Validators>),
+    /// failed, for a given reason
+    Failed(String),
+}
+
+#[derive(Tabled)]
+struct SummaryLine<'a> {
+    #[tabled(rename = "Bin")]
+    bin: &'a str,
+    #[tabled(rename = "Result")]
+    result: &'static str,
+    #[tabled(rename = "Installed")]
+    installed: String,
+    #[tabled(rename = "Reason")]
+    reason: String,
+}
 
 /// Update installed packages
 pub async fn update(args: UpdateArgs) -> Result<()> {
@@ -59,120 +98,104 @@ pub async fn update(args: UpdateArgs) -> Result<()> {
         packages_to_update
     };
 
-    let gh = GitHub::create(args.token.as_ref());
-    let temp_dir = tempfile::tempdir().context("creating a temp dir failed")?;
+    let progress = MultiProgress::new();
+    let mut forge_opts = args.forge_opts.forge_options();
+    forge_opts.progress = Some(progress.clone());
     let default_bin_dir = util::bin_dir()?;
-    let mut needs_save = false;
     let mut updated = 0;
+    let mut failed = 0;
+    // (package index, result, installed, reason)
+    let mut summary = Vec::new();
 
-    for i in packages_to_update {
-        let pb = ProgressBar::new(u64::MAX);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("{spinner:.green} {msg}")
-                .unwrap()
-                .progress_chars("##-"),
-        );
-        pb.set_message(format!(
-            "searching for {}",
-            style(&packages_installed[i].bin_name).green()
-        ));
-        pb.enable_steady_tick(Duration::from_millis(220));
-
-        match gh.find_existing(&packages_installed[i]).await {
-            Ok(release) => {
-                pb.set_message(format!(
-                    "downloading {}",
-                    style(&packages_installed[i].bin_name).green()
-                ));
+    // packages are updated concurrently, each working on its own copy of a `Package`,
+    // while the results are applied, and written out, one at a time as they come in
+    let mut packages_to_update: Vec<_> = packages_to_update
+        .into_iter()
+        .map(|i| (i, packages_installed[i].clone()))
+        .collect();
+    packages_to_update.sort_unstable_by_key(|(i, _)| *i);
+    let tasks = packages_to_update.into_iter().map(|(i, pkg)| {
+        let progress = &progress;
+        let forge_opts = &forge_opts;
+        let default_bin_dir = default_bin_dir.as_path();
+        let args = &args;
+        async move {
+            let pb = progress.add(ProgressBar::new(u64::MAX));
+            let res = update_package(&pkg, args, forge_opts, default_bin_dir, &pb).await;
+            (i, pb, res)
+        }
+    });
+    let mut results = stream::iter(tasks).buffer_unordered(args.jobs.max(1));
 
-                let asset_path = gh
-                    .download(
-                        &packages_installed[i].user,
-                        &packages_installed[i].repo,
-                        release.assets[0].id,
-                        &release.assets[0].name,
-                        &temp_dir,
-                    )
-                    .await?;
-
-                pb.set_message(format!(
-                    "updating {}",
-                    style(&packages_installed[i].bin_name).green()
-                ));
+    // a failed package doesn't stop the others
+    while let Some((i, pb, res)) = results.next().await {
+        let pkg = &mut packages_installed[i];
 
-                // either use the default path or the one specified in a package spec
-                let pkg_path;
-                let bin_dir = if let Some(p) = &packages_installed[i].path {
-                    pkg_path = std::path::PathBuf::try_from(p)?;
-                    pkg_path.as_path()
-                } else {
-                    default_bin_dir.as_path()
-                };
-
-                #[cfg(not(target_os = "windows"))]
-                let res = installer::install(
-                    &release.assets[0].name,
-                    &asset_path,
-                    bin_dir,
-                    &packages_installed[i].bin_name,
-                    packages_installed[i].strip.unwrap_or_default(),
-                    packages_installed[i].entry_glob.as_deref(),
-                    packages_installed[i].entry_re.as_deref(),
-                )
-                .await;
-
-                #[cfg(target_os = "windows")]
-                let res = installer::install(
-                    &release.assets[0].name,
-                    &asset_path,
-                    bin_dir,
-                    &packages_installed[i].bin_name,
-                    packages_installed[i].asset_glob.as_deref(),
-                    packages_installed[i].asset_re.as_deref(),
-                )
-                .await;
-
-                match res {
-                    Ok(bin_size) => {
-                        #[cfg(not(target_os = "windows"))]
-                        if let Some(cmd) = &packages_installed[i].cmd_after {
-                            let bin_path = bin_dir.join(&packages_installed[i].bin_name);
-                            executor::exec(&bin_path, cmd)?;
-                        }
-
-                        packages_installed[i].tag = release.tag_name;
-                        packages_installed[i].timestamp = release.published_at;
-
-                        let msg = format!(
-                            "{} updated {} ({})",
-                            style('✓').green(),
-                            style(&packages_installed[i].bin_name).green(),
-                            bytesize::to_string(bin_size, false),
-                        );
-                        pb.set_style(ProgressStyle::default_bar().template("{msg}").unwrap());
-                        pb.finish_with_message(msg);
-
-                        needs_save = true;
-                        updated += 1;
-                    }
-                    Err(e) => {
-                        util::message_fail(&pb, &packages_installed[i].bin_name, "not updated");
-
-                        use crate::domain::error::InstallerError;
-                        match e {
-                            InstallerError::AnyHow(e) => {
-                                return Err(e);
-                            }
-                            e => {
-                                eprint!("\nreason: {}\n\n", e);
-                                return Ok(());
-                            }
-                        }
-                    }
+        match res.unwrap_or_else(|e| Outcome::Failed(format!("{:#}", e))) {
+            Outcome::Updated {
+                release,
+                bin_size,
+                sha256,
+                bins,
+                side_files,
+            } => {
+                summary.push((
+                    i,
+                    "updated",
+                    format!("{} -> {}", &pkg.tag, &release.tag_name),
+                    String::new(),
+                ));
+                pkg.tag = release.tag_name;
+                pkg.validators = release.validators;
+                pkg.timestamp = release.published_at;
+                pkg.sha256 = Some(sha256);
+                pkg.size = Some(bin_size);
+                pkg.bins = bins;
+                // i.e. no longer shipped with a new release
+                if let Err(e) = side_files::remove(&pkg.side_files, &side_files) {
+                    util::message_warn(&pb, &format!("{}: {:#}", &pkg.bin_name, e));
+                }
+                pkg.side_files = side_files;
+                let bin_dir = pkg
+                    .path
+                    .as_ref()
+                    .map_or_else(|| default_bin_dir.clone(), PathBuf::from);
+                // an explicit `--keep` is recorded, as if the package was installed with it
+                if args.keep.is_some() {
+                    pkg.keep = args.keep;
+                }
+                if let Err(e) = store::keep(pkg, &bin_dir) {
+                    util::message_warn(
+                        &pb,
+                        &format!("{}: not kept to roll back to: {:#}", &pkg.bin_name, e),
+                    );
                 }
+                package::write_packages_file(&packages_file, &packages_installed)?;
+
+                let msg = format!(
+                    "{} updated {} ({})",
+                    style('✓').green(),
+                    style(&packages_installed[i].bin_name).green(),
+                    bytesize::to_string(bin_size, false),
+                );
+                pb.set_style(ProgressStyle::default_bar().template("{msg}").unwrap());
+                pb.finish_with_message(msg);
+
+                updated += 1;
             }
-            Err(GithubError::AlreadyUpToDate) => {
+            Outcome::UpToDate(validators) => {
+                // i.e. a package installed before validators were recorded
+                if validators.is_some() && validators != pkg.validators {
+                    pkg.validators = validators;
+                    package::write_packages_file(&packages_file, &packages_installed)?;
+                }
+                summary.push((
+                    i,
+                    "up to date",
+                    packages_installed[i].tag.clone(),
+                    String::new(),
+                ));
+
                 let msg = format!(
                     "{} already up to date {}",
                     style('✓').green(),
@@ -181,25 +204,25 @@ pub async fn update(args: UpdateArgs) -> Result<()> {
                 pb.set_style(ProgressStyle::default_bar().template("{msg}").unwrap());
                 pb.finish_with_message(msg);
             }
-            Err(e) => {
-                util::message_fail(&pb, &packages_installed[i].bin_name, "failed to update");
-
-                match e {
-                    GithubError::AnyHow(e) => {
-                        return Err(e);
-                    }
-                    e => {
-                        eprint!("\nreason: {}\n\n", e);
-                        return Ok(());
-                    }
-                }
+            Outcome::Failed(reason) => {
+                util::message_fail(&pb, &pkg.bin_name, "failed to update");
+                summary.push((i, "failed", pkg.tag.clone(), reason));
+                failed += 1;
             }
         }
     }
 
-    if needs_save {
-        package::write_packages_file(&packages_file, &packages_installed)?;
-    }
+    summary.sort_unstable_by_key(|(i, ..)| *i);
+    let summary: Vec<_> = summary
+        .into_iter()
+        .map(|(i, result, installed, reason)| SummaryLine {
+            bin: &packages_installed[i].bin_name,
+            result,
+            installed,
+            reason,
+        })
+        .collect();
+    println!("\n{}", create_table(&summary));
 
     let requested_tot = if args.bin_names.is_empty() {
         packages_installed.len()
@@ -208,5 +231,256 @@ pub async fn update(args: UpdateArgs) -> Result<()> {
     };
     println!("\nUpdated {} of {} binaries.", updated, requested_tot);
 
+    if failed > 0 {
+        return Err(UpdateFailed(failed).into());
+    }
+
     Ok(())
 }
+
+fn create_table(data: &[SummaryLine]) -> Table {
+    let st_result = |s: &str| match s {
+        "failed" => s.red().to_string(),
+        "updated" => s.cyan().to_string(),
+        _ => s.green().to_string(),
+    };
+
+    let theme = Style::modern()
+        .off_top()
+        .off_bottom()
+        .off_horizontal()
+        // NB: order matters, make sure `.lines` is before `off_left|off_right`
+        .lines([(1, Style::modern().get_horizontal())])
+        .off_left()
+        .off_right();
+
+    Table::new(data)
+        .with(
+            Columns::single(0)
+                .modify()
+                .with(|s: &str| s.green().to_string()),
+        )
+        .with(Columns::single(1).modify().with(st_result))
+        .with(theme)
+}
+
+/// Update a single package, reporting progress with `pb`. The caller
+/// is responsible for reporting, and recording the outcome.
+async fn update_package(
+    pkg: &Package,
+    args: &UpdateArgs,
+    forge_opts: &ForgeOptions,
+    default_bin_dir: &Path,
+    pb: &ProgressBar,
+) -> Result<Outcome> {
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} {msg}")
+            .unwrap()
+            .progress_chars("##-"),
+    );
+    pb.set_message(format!("searching for {}", style(&pkg.bin_name).green()));
+    pb.enable_steady_tick(Duration::from_millis(220));
+
+    // a package installed from a custom API root keeps using it, and `--api-url` is one
+    // of GitHub (Enterprise), not of other forges
+    let api_url = pkg.api_url.as_deref().or_else(|| {
+        args.api_url
+            .as_deref()
+            .filter(|_| pkg.forge == ForgeKind::GitHub)
+    });
+    let gh = forge::create(
+        pkg.forge,
+        args.tokens.token_for(pkg.forge),
+        api_url,
+        pkg.url.as_ref(),
+        forge_opts,
+    )?;
+
+    let release = match gh.find_existing(pkg).await {
+        Ok(release) => release,
+        Err(GithubError::AlreadyUpToDate(validators)) => {
+            return Ok(Outcome::UpToDate(validators));
+        }
+        Err(GithubError::AnyHow(e)) => return Err(e),
+        Err(e) => return Ok(Outcome::Failed(e.to_string())),
+    };
+
+    pb.set_message(format!("downloading {}", style(&pkg.bin_name).green()));
+
+    // a temp dir per package, since assets of different packages may share a name
+    let temp_dir = tempfile::tempdir().context("creating a temp dir failed")?;
+    let asset_path = gh
+        .download(&pkg.user, &pkg.repo, &release.assets[0], &temp_dir)
+        .await?;
+
+    pb.set_message(format!("verifying {}", style(&pkg.bin_name).green()));
+    let verification = checksum::verify(
+        gh.as_ref(),
+        &pkg.user,
+        &pkg.repo,
+        &release,
+        &asset_path,
+        &temp_dir,
+        pkg.checksum,
+    )
+    .await;
+    match verification {
+        Ok(Verification::Unverified(reason)) => {
+            util::message_warn(pb, &format!("{}: {}", &pkg.bin_name, reason))
+        }
+        Ok(_) => (),
+        Err(GithubError::AnyHow(e)) => return Err(e),
+        Err(e) => return Ok(Outcome::Failed(e.to_string())),
+    }
+
+    if let Some(public_key) = &pkg.public_key {
+        let verification = signature::verify(
+            gh.as_ref(),
+            &pkg.user,
+            &pkg.repo,
+            &release,
+            &asset_path,
+            &temp_dir,
+            public_key,
+        )
+        .await;
+        match verification {
+            Ok(()) => (),
+            Err(InstallerError::AnyHow(e)) => return Err(e),
+            Err(e) => return Ok(Outcome::Failed(e.to_string())),
+        }
+    }
+
+    if let Some(signer) = &pkg.sigstore {
+        let verification = sigstore::verify(
+            gh.as_ref(),
+            &pkg.user,
+            &pkg.repo,
+            &release,
+            &asset_path,
+            &temp_dir,
+            signer,
+        )
+        .await;
+        match verification {
+            Ok(()) => (),
+            Err(InstallerError::AnyHow(e)) => return Err(e),
+            Err(e) => return Ok(Outcome::Failed(e.to_string())),
+        }
+    }
+
+    pb.set_message(format!("updating {}", style(&pkg.bin_name).green()));
+
+    // either use the default path or the one specified in a package spec
+    let pkg_path;
+    let bin_dir = if let Some(p) = &pkg.path {
+        pkg_path = std::path::PathBuf::from(p);
+        pkg_path.as_path()
+    } else {
+        default_bin_dir
+    };
+
+    let store_dir = match pkg.layout {
+        Layout::Link => Some(store::create_version_dir(&pkg.bin_name, &release.tag_name)?),
+        Layout::Copy => None,
+    };
+
+    // extracting, stripping and fingerprinting binaries would otherwise hold up other jobs
+    let installed = {
+        let pkg = pkg.clone();
+        let asset_name = release.assets[0].name.clone();
+        let bin_dir = bin_dir.to_path_buf();
+        let pb = pb.clone();
+        util::blocking(move || {
+            install(
+                &pkg,
+                &asset_name,
+                &asset_path,
+                &bin_dir,
+                store_dir.as_deref(),
+                &pb,
+            )
+        })
+        .await
+    };
+
+    match installed {
+        Ok((bin_size, sha256, bins, side_files)) => Ok(Outcome::Updated {
+            release: Box::new(release),
+            bin_size,
+            sha256,
+            bins,
+            side_files,
+        }),
+        Err(InstallerError::AnyHow(e)) => Err(e),
+        Err(e) => Ok(Outcome::Failed(e.to_string())),
+    }
+}
+
+/// Install binaries of `pkg` from a downloaded asset, and its side files. Returns the main
+/// binary size and SHA-256 digest, extra binaries with theirs, and installed side files.
+fn install(
+    pkg: &Package,
+    asset_name: &str,
+    asset_path: &Path,
+    bin_dir: &Path,
+    store_dir: Option<&Path>,
+    pb: &ProgressBar,
+) -> Result<(u64, String, Vec<ExtraBin>, Vec<PathBuf>), InstallerError> {
+    #[cfg(not(target_os = "windows"))]
+    installer::install(
+        asset_name,
+        asset_path,
+        bin_dir,
+        &pkg.bin_name,
+        &pkg.bins,
+        store_dir,
+        pkg.strip.unwrap_or_default(),
+        pkg.ignore_arch.unwrap_or_default(),
+        pkg.entry_glob.as_deref(),
+        pkg.entry_re.as_deref(),
+    )?;
+
+    #[cfg(target_os = "windows")]
+    installer::install(
+        asset_name,
+        asset_path,
+        bin_dir,
+        &pkg.bin_name,
+        &pkg.bins,
+        store_dir,
+        pkg.ignore_arch.unwrap_or_default(),
+        pkg.asset_glob.as_deref(),
+        pkg.asset_re.as_deref(),
+    )?;
+
+    #[cfg(not(target_os = "windows"))]
+    if let Some(cmd) = &pkg.cmd_after {
+        let bin_path = bin_dir.join(&pkg.bin_name);
+        executor::exec(&bin_path, cmd)?;
+    }
+
+    // as left on disk, after `cmd_after`, for `verify`
+    let (sha256, bin_size) = checksum::fingerprint(&util::bin_path(bin_dir, &pkg.bin_name))
+        .map_err(anyhow::Error::from)?;
+    let mut bins = pkg.bins.clone();
+    checksum::fingerprint_bins(bin_dir, &mut bins).map_err(anyhow::Error::from)?;
+
+    let side_files = if pkg.no_side_files.unwrap_or_default() {
+        Vec::new()
+    } else {
+        let bin_names: Vec<_> = std::iter::once(pkg.bin_name.as_str())
+            .chain(bins.iter().map(ExtraBin::bin_name))
+            .collect();
+        installer::install_side_files(asset_name, asset_path, &bin_names).unwrap_or_else(|e| {
+            util::message_warn(
+                pb,
+                &format!("{}: side files not updated: {:#}", &pkg.bin_name, e),
+            );
+            pkg.side_files.clone()
+        })
+    };
+
+    Ok((bin_size, sha256, bins, side_files))
+}
@@ -0,0 +1,226 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use clap::crate_name;
+use owo_colors::OwoColorize;
+use tabled::{object::Columns, style::Style, ModifyObject, Table, Tabled};
+
+use crate::cli::VerifyArgs;
+use crate::domain::checksum;
+use crate::domain::error::VerifyFailed;
+use crate::domain::package::{self, Package};
+use crate::domain::store::{self, Layout};
+use crate::domain::util::{self, packages_file};
+
+#[derive(Tabled)]
+struct VerifyLine {
+    #[tabled(rename = "Bin")]
+    bin: String,
+    #[tabled(rename = "Result")]
+    result: &'static str,
+    #[tabled(rename = "Path")]
+    path: String,
+    #[tabled(rename = "Reason")]
+    reason: String,
+}
+
+/// Verify installed binaries against their digests recorded at install/update
+pub fn verify(args: VerifyArgs) -> Result<()> {
+    let packages_file = packages_file()?;
+    let packages_installed = package::read_packages_file(&packages_file)?;
+
+    if packages_installed.is_empty() {
+        println!(
+                "No managed installationts on this system. Use `{} install repo@[*|name|semver]...` to install package(s)",
+                crate_name!(),
+            );
+        return Ok(());
+    }
+
+    let mut requested_packages: HashSet<_> = args.bin_names.iter().collect();
+    let packages_to_verify: Vec<_> = packages_installed
+        .iter()
+        .filter(|pkg| args.bin_names.is_empty() || requested_packages.remove(&pkg.bin_name))
+        .collect();
+
+    if !requested_packages.is_empty() {
+        for bin_name in &requested_packages {
+            eprintln!("\npackage `{}` is not installed", bin_name);
+        }
+        return Err(anyhow!(
+            "{} package(s) not installed",
+            requested_packages.len()
+        ));
+    }
+
+    let default_bin_dir = util::bin_dir()?;
+    let mut lines = Vec::with_capacity(packages_to_verify.len());
+    // managed binaries by install dir, to tell unmanaged files in there apart
+    let mut managed: BTreeMap<PathBuf, HashSet<PathBuf>> = BTreeMap::new();
+
+    for pkg in packages_to_verify {
+        let bin_dir = pkg
+            .path
+            .as_ref()
+            .map_or_else(|| default_bin_dir.clone(), PathBuf::from);
+        let main_bin = (pkg.bin_name.as_str(), pkg.sha256.as_deref(), pkg.size);
+        let extra_bins = pkg.bins.iter().map(|extra_bin| {
+            (
+                extra_bin.bin_name(),
+                extra_bin.sha256.as_deref(),
+                extra_bin.size,
+            )
+        });
+
+        for (bin_name, sha256, size) in std::iter::once(main_bin).chain(extra_bins) {
+            let bin_path = util::bin_path(&bin_dir, bin_name);
+            let (result, reason) = check(pkg, bin_name, sha256, size, &bin_path);
+
+            lines.push(VerifyLine {
+                bin: bin_name.to_owned(),
+                result,
+                path: bin_path.display().to_string(),
+                reason,
+            });
+            managed.entry(bin_dir.clone()).or_default().insert(bin_path);
+        }
+    }
+    let binaries_tot = lines.len();
+
+    // files in install dirs, that weren't installed by `gitrel`, are only looked for
+    // when verifying all binaries, since the others may live in the same dirs
+    if args.bin_names.is_empty() {
+        for (bin_dir, bin_paths) in &managed {
+            let entries = match fs::read_dir(bin_dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+
+            let mut foreign: Vec<_> = entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| {
+                    !entry.file_name().to_string_lossy().starts_with('.')
+                        && entry.file_type().is_ok_and(|t| !t.is_dir())
+                        && !bin_paths.contains(&entry.path())
+                })
+                .map(|entry| entry.path())
+                .collect();
+            foreign.sort_unstable();
+
+            lines.extend(foreign.into_iter().map(|path| {
+                VerifyLine {
+                    bin: path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                    result: "foreign",
+                    path: path.display().to_string(),
+                    reason: format!("not installed by {}", crate_name!()),
+                }
+            }));
+        }
+    }
+
+    println!("\n{}", create_table(&lines));
+
+    let verified = lines.iter().filter(|line| line.result == "ok").count();
+    // unmanaged files are merely reported, unless `--strict`
+    let failed = lines
+        .iter()
+        .filter(|line| match line.result {
+            "ok" | "unrecorded" => false,
+            "foreign" => args.strict,
+            _ => true,
+        })
+        .count();
+    println!("\nVerified {} of {} binaries.", verified, binaries_tot);
+
+    if failed > 0 {
+        return Err(VerifyFailed(failed).into());
+    }
+
+    Ok(())
+}
+
+/// A result of verifying a binary `bin_name` of `pkg` at `bin_path`, against its recorded
+/// `sha256` digest and `size`, and a reason of a failure.
+fn check(
+    pkg: &Package,
+    bin_name: &str,
+    sha256: Option<&str>,
+    size: Option<u64>,
+    bin_path: &Path,
+) -> (&'static str, String) {
+    if fs::symlink_metadata(bin_path).is_err() {
+        return ("missing", "no such file".to_owned());
+    }
+
+    // i.e. relinked to some other binary, which would then be verified instead
+    if pkg.layout == Layout::Link {
+        if let (Ok(target), Ok(expected)) = (
+            fs::read_link(bin_path),
+            store::stored_path(pkg, &pkg.tag, bin_name),
+        ) {
+            if target != expected {
+                return (
+                    "modified",
+                    format!("links to {}, not {}", target.display(), expected.display()),
+                );
+            }
+        }
+    }
+
+    let (sha256, size) = match (sha256, size) {
+        (Some(sha256), Some(size)) => (sha256, size),
+        _ => {
+            return (
+                "unrecorded",
+                "installed before digests were recorded, `update` or reinstall to record"
+                    .to_owned(),
+            )
+        }
+    };
+
+    match checksum::fingerprint(bin_path) {
+        Ok((actual, _)) if actual == sha256 => ("ok", String::new()),
+        Ok((_, actual_size)) if actual_size != size => (
+            "modified",
+            format!(
+                "size is {}, not {}",
+                bytesize::to_string(actual_size, false),
+                bytesize::to_string(size, false)
+            ),
+        ),
+        Ok((actual, _)) => ("modified", format!("SHA-256 is {}, not {}", actual, sha256)),
+        Err(e) => ("unreadable", e.to_string()),
+    }
+}
+
+fn create_table(data: &[VerifyLine]) -> Table {
+    let st_result = |s: &str| match s {
+        "modified" | "missing" | "unreadable" => s.red().to_string(),
+        "unrecorded" | "foreign" => s.yellow().to_string(),
+        _ => s.green().to_string(),
+    };
+
+    let theme = Style::modern()
+        .off_top()
+        .off_bottom()
+        .off_horizontal()
+        // NB: order matters, make sure `.lines` is before `off_left|off_right`
+        .lines([(1, Style::modern().get_horizontal())])
+        .off_left()
+        .off_right();
+
+    Table::new(data)
+        .with(
+            Columns::single(0)
+                .modify()
+                .with(|s: &str| s.green().to_string()),
+        )
+        .with(Columns::single(1).modify().with(st_result))
+        .with(theme)
+}
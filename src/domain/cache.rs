@@ -0,0 +1,175 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use tempfile::TempDir;
+use url::Url;
+
+use super::github::Asset;
+use super::util;
+
+/// Cache sub-directory of complete assets.
+pub const ASSETS_DIR: &str = "assets";
+/// Cache sub-directory of partial downloads (see `forge::download`).
+pub const PARTIAL_DIR: &str = "partial";
+/// File name prefix of assets being copied into the cache.
+const PART_PREFIX: &str = ".part";
+
+/// A content cache of downloaded assets, under `~/.cache/gitrel/assets`.
+///
+/// Entries are keyed by an API host, an asset id, and the time an asset was last
+/// updated, so a re-uploaded asset is never served from the cache.
+pub struct AssetCache {
+    dir: PathBuf,
+}
+
+/// An asset in the cache, or a partial download of one.
+pub struct CacheEntry {
+    pub key: String,
+    pub name: String,
+    pub size: u64,
+    pub modified: SystemTime,
+    pub partial: bool,
+    path: PathBuf,
+}
+
+impl AssetCache {
+    pub fn open() -> Result<Self> {
+        Ok(Self {
+            dir: util::cache_dir(ASSETS_DIR)?,
+        })
+    }
+
+    /// A cache key of an `asset` served by an API at `api_url`.
+    pub fn key(api_url: &str, asset: &Asset) -> String {
+        let host = Url::parse(api_url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_owned))
+            .unwrap_or_default();
+        format!("{}-{}-{}", host, asset.id, asset.updated_at.timestamp())
+    }
+
+    /// Copy a cached `asset` into `temp_dir`, returning a path to the copy,
+    /// or `None` if it isn't cached.
+    pub fn get(&self, key: &str, asset: &Asset, temp_dir: &TempDir) -> Option<PathBuf> {
+        let path = self.dir.join(key).join(&asset.name);
+        let len = fs::metadata(&path).ok()?.len();
+        if asset.size != 0 && asset.size != len {
+            return None;
+        }
+
+        let temp_file_name = temp_dir.path().join(&asset.name);
+        fs::copy(&path, &temp_file_name).ok()?;
+
+        // so that `cache clean --older-than` goes by the last use
+        if let Ok(file) = File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+
+        Some(temp_file_name)
+    }
+
+    /// Add a downloaded asset at `path` to the cache.
+    pub fn put(&self, key: &str, path: &Path) -> Result<()> {
+        let file_name = path.file_name().context("cached asset has no file name")?;
+        let entry_dir = self.dir.join(key);
+        fs::create_dir_all(&entry_dir)
+            .with_context(|| format!("creating a cache entry: {:?}", entry_dir))?;
+
+        // copy under a temporary name first, so that an interrupted
+        // (or a concurrent) copy is never used
+        let cached = entry_dir.join(file_name);
+        let part = tempfile::Builder::new()
+            .prefix(PART_PREFIX)
+            .tempfile_in(&entry_dir)
+            .with_context(|| format!("caching an asset: {:?}", cached))?;
+        fs::copy(path, part.path()).with_context(|| format!("caching an asset: {:?}", cached))?;
+        part.persist(&cached)
+            .with_context(|| format!("caching an asset: {:?}", cached))?;
+
+        Ok(())
+    }
+
+    /// All cached assets and partial downloads, least recently used first.
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+
+        for entry_dir in read_dir(&self.dir)? {
+            let key = entry_dir.file_name().to_string_lossy().into_owned();
+            for file in read_dir(&entry_dir.path())? {
+                let name = file.file_name().to_string_lossy().into_owned();
+                if name.starts_with(PART_PREFIX) {
+                    continue;
+                }
+                if let Ok(meta) = file.metadata() {
+                    entries.push(CacheEntry {
+                        key: key.clone(),
+                        name,
+                        size: meta.len(),
+                        modified: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                        partial: false,
+                        path: file.path(),
+                    });
+                }
+            }
+        }
+
+        // partial downloads are named `<url hash>-<asset name>`, see `forge::download`
+        for file in read_dir(&util::cache_dir(PARTIAL_DIR)?)? {
+            let file_name = file.file_name().to_string_lossy().into_owned();
+            if file_name.ends_with(".json") {
+                continue;
+            }
+            let (key, name) = file_name.split_once('-').unwrap_or(("", &file_name));
+            if let Ok(meta) = file.metadata() {
+                entries.push(CacheEntry {
+                    key: key.to_owned(),
+                    name: name.to_owned(),
+                    size: meta.len(),
+                    modified: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                    partial: true,
+                    path: file.path(),
+                });
+            }
+        }
+
+        entries.sort_by_key(|entry| entry.modified);
+        Ok(entries)
+    }
+
+    /// Remove entries not used for longer than `older_than`, or all of them.
+    /// Returns the number of removed entries, and their total size.
+    pub fn clean(&self, older_than: Option<Duration>) -> Result<(usize, u64)> {
+        let now = SystemTime::now();
+        let mut removed = (0, 0);
+
+        for entry in self.entries()? {
+            let age = now.duration_since(entry.modified).unwrap_or_default();
+            if older_than.is_some_and(|older_than| age < older_than) {
+                continue;
+            }
+
+            if entry.partial {
+                let mut meta_path = entry.path.clone().into_os_string();
+                meta_path.push(".json");
+                let _ = fs::remove_file(meta_path);
+                fs::remove_file(&entry.path)
+            } else {
+                fs::remove_dir_all(entry.path.parent().unwrap_or(&entry.path))
+            }
+            .with_context(|| format!("removing a cache entry: {:?}", entry.path))?;
+
+            removed.0 += 1;
+            removed.1 += entry.size;
+        }
+
+        Ok(removed)
+    }
+}
+
+fn read_dir(dir: &Path) -> Result<impl Iterator<Item = fs::DirEntry>> {
+    Ok(fs::read_dir(dir)
+        .with_context(|| format!("reading cache dir: {:?}", dir))?
+        .filter_map(|entry| entry.ok()))
+}
@@ -0,0 +1,236 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use tempfile::TempDir;
+
+use super::error::GithubError;
+use super::forge::Forge;
+use super::github::{Asset, Release};
+use super::package::ExtraBin;
+use super::util;
+
+type Result<T, E = GithubError> = std::result::Result<T, E>;
+
+/// What to do about checksums published alongside a release asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumPolicy {
+    /// fail, unless an asset is verified
+    Require,
+    /// verify an asset when checksums are published, warn when they aren't
+    #[default]
+    Warn,
+    /// don't verify
+    Off,
+}
+
+/// An outcome of a checksum verification, that didn't fail.
+pub enum Verification {
+    /// verified with a given algorithm
+    Verified(&'static str),
+    /// not verified, for a given reason
+    Unverified(String),
+    /// verification is turned off
+    Skipped,
+}
+
+/// Checksum assets among `assets`, that may cover an asset named `asset_name`.
+/// Dedicated ones (i.e. `<asset>.sha256`) come before those listing several assets
+/// (i.e. `SHA256SUMS`, `checksums.txt`).
+pub fn sibling_assets(assets: &[Asset], asset_name: &str) -> Vec<Asset> {
+    let asset_name = asset_name.to_lowercase();
+    let dedicated = |name: &str| {
+        name.strip_prefix(&asset_name)
+            .and_then(|ext| ext.strip_prefix('.'))
+            .map(|ext| ext.trim_end_matches(".txt"))
+            .is_some_and(|ext| matches!(ext, "sha256" | "sha256sum" | "sha512" | "sha512sum"))
+    };
+    let listing = |name: &str| {
+        (name.contains("checksums") || name.contains("sha256sums") || name.contains("sha512sums"))
+            // signatures of checksum files are not checksum files
+            && ![".sig", ".asc", ".pem", ".minisig", ".sigstore", ".sigstore.json", ".bundle", ".cert"]
+                .iter()
+                .any(|ext| name.ends_with(ext))
+    };
+
+    let mut dedicated_assets = Vec::new();
+    let mut listing_assets = Vec::new();
+    for asset in assets {
+        let name = asset.name.to_lowercase();
+        if dedicated(&name) {
+            dedicated_assets.push(asset.clone());
+        } else if listing(&name) {
+            listing_assets.push(asset.clone());
+        }
+    }
+
+    dedicated_assets.extend(listing_assets);
+    dedicated_assets
+}
+
+/// Verify a downloaded `release` asset at `asset_path` against the checksums published
+/// with it, as per `policy`. A checksum mismatch fails regardless of the policy.
+pub async fn verify(
+    forge: &dyn Forge,
+    user: &str,
+    repo: &str,
+    release: &Release,
+    asset_path: &Path,
+    temp_dir: &TempDir,
+    policy: ChecksumPolicy,
+) -> Result<Verification> {
+    // there's nothing to warn about, when there can't be a checksum
+    if policy == ChecksumPolicy::Off
+        || (policy == ChecksumPolicy::Warn && !forge.publishes_checksums())
+    {
+        return Ok(Verification::Skipped);
+    }
+
+    let asset_name = &release.assets[0].name;
+
+    for checksum_asset in &release.checksum_assets {
+        let checksum_path = forge.download(user, repo, checksum_asset, temp_dir).await?;
+        let checksums = fs::read_to_string(&checksum_path)
+            .with_context(|| format!("reading checksums: {}", &checksum_asset.name))?;
+
+        let checksums_name = checksum_asset.name.clone();
+        let name = asset_name.clone();
+        let path = asset_path.to_path_buf();
+        let verified =
+            util::blocking(move || verify_listed(&checksums, &checksums_name, &name, &path))
+                .await?;
+
+        // i.e. a checksums file of some other build
+        if let Some(algorithm) = verified {
+            return Ok(Verification::Verified(algorithm));
+        }
+    }
+
+    let reason = format!("no SHA-256/SHA-512 checksum published for {}", asset_name);
+    match policy {
+        ChecksumPolicy::Require => Err(GithubError::Integrity(format!(
+            "{}, as required by the checksum policy",
+            reason
+        ))),
+        _ => Ok(Verification::Unverified(reason)),
+    }
+}
+
+/// Verify an asset at `asset_path` against its checksum in `checksums`, read from
+/// `checksums_name`. Returns the algorithm used, or `None` if the asset isn't listed.
+pub fn verify_listed(
+    checksums: &str,
+    checksums_name: &str,
+    asset_name: &str,
+    asset_path: &Path,
+) -> Result<Option<&'static str>> {
+    let expected = match find_checksum(checksums, asset_name) {
+        Some(expected) => expected,
+        None => return Ok(None),
+    };
+
+    let (algorithm, actual) = match expected.len() {
+        64 => ("sha256", digest::<Sha256>(asset_path)?),
+        128 => ("sha512", digest::<Sha512>(asset_path)?),
+        _ => return Ok(None),
+    };
+
+    if actual == expected {
+        Ok(Some(algorithm))
+    } else {
+        Err(GithubError::Integrity(format!(
+            "{} {} checksum mismatch, expected {} as per {}, got {}",
+            asset_name, algorithm, expected, checksums_name, actual
+        )))
+    }
+}
+
+/// A lowercase hex checksum of `asset_name` in `checksums`, which is either a checksum alone,
+/// or a list in `sha256sum` (`<hex> [*]<name>`) or BSD (`SHA256 (<name>) = <hex>`) format.
+fn find_checksum(checksums: &str, asset_name: &str) -> Option<String> {
+    let is_hex = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit());
+    // listed names may include a path, i.e. `./dist/tool.tar.gz`
+    let is_asset = |name: &str| name.rsplit('/').next() == Some(asset_name);
+
+    let lines: Vec<_> = checksums
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    if let [line] = lines[..] {
+        if is_hex(line) {
+            return Some(line.to_lowercase());
+        }
+    }
+
+    lines.into_iter().find_map(|line| {
+        let (hex, name) = match line.split_once(" (") {
+            Some((_, rest)) => {
+                let (name, hex) = rest.split_once(") = ")?;
+                (hex, name)
+            }
+            None => {
+                let (hex, name) = line.split_once(char::is_whitespace)?;
+                (hex, name.trim_start().trim_start_matches('*'))
+            }
+        };
+        (is_hex(hex) && is_asset(name)).then(|| hex.to_lowercase())
+    })
+}
+
+/// A SHA-256 digest and a size of a file at `path`, i.e. of an installed binary.
+pub fn fingerprint(path: &Path) -> Result<(String, u64)> {
+    let size = fs::metadata(path)
+        .with_context(|| format!("reading metadata: {:?}", path))?
+        .len();
+    Ok((digest::<Sha256>(path)?, size))
+}
+
+/// Record SHA-256 digests and sizes of `extra_bins` installed into `bin_dir`.
+pub fn fingerprint_bins(bin_dir: &Path, extra_bins: &mut [ExtraBin]) -> Result<()> {
+    for extra_bin in extra_bins {
+        let (sha256, size) = fingerprint(&util::bin_path(bin_dir, extra_bin.bin_name()))?;
+        extra_bin.sha256 = Some(sha256);
+        extra_bin.size = Some(size);
+    }
+    Ok(())
+}
+
+fn digest<D: Digest + io::Write>(path: &Path) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("opening {:?}", path))?;
+    let mut hasher = D::new();
+    io::copy(&mut file, &mut hasher).context("computing a checksum")?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_checksums() {
+        let hex = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+        assert_eq!(find_checksum(hex, "tool.tgz").as_deref(), Some(hex));
+
+        let sums = format!(
+            "{}  tool-arm64.tgz\n{} *./dist/tool.tgz\n",
+            "0".repeat(64),
+            hex
+        );
+        assert_eq!(find_checksum(&sums, "tool.tgz").as_deref(), Some(hex));
+        assert_eq!(find_checksum(&sums, "other.tgz"), None);
+
+        let bsd = format!("SHA256 (tool.tgz) = {}", hex.to_uppercase());
+        assert_eq!(find_checksum(&bsd, "tool.tgz").as_deref(), Some(hex));
+    }
+}
@@ -1,3 +1,7 @@
+use chrono::{DateTime, Utc};
+
+use super::forge::Validators;
+
 #[derive(thiserror::Error, Debug)]
 pub enum GithubError {
     #[error("repository/release not found")]
@@ -12,18 +16,109 @@ pub enum GithubError {
     #[error("asset file not found")]
     AssetNoMatch,
 
+    /// carries validators of a release endpoint response, to record for the next
+    /// conditional request, or `None` when the endpoint replied with `304 Not Modified`
     #[error("already up to date")]
-    AlreadyUpToDate,
+    AlreadyUpToDate(Option<Validators>),
+
+    #[error("integrity check failed: {0}")]
+    Integrity(String),
+
+    #[error("API rate limit exceeded, resets at {}\nconsider using `--token`, or `--wait-rate-limit` to wait for a reset", .reset_at.with_timezone(&chrono::Local).format("%H:%M:%S"))]
+    RateLimited { reset_at: DateTime<Utc> },
 
     #[error(transparent)]
     AnyHow(#[from] anyhow::Error), // source and Display delegate to anyhow::Error
 }
 
+/// Some packages failed to update, the reasons having been reported already.
+#[derive(thiserror::Error, Debug)]
+#[error("{0} package(s) failed to update")]
+pub struct UpdateFailed(pub usize);
+
+/// Some files failed verification, the reasons having been reported already.
+#[derive(thiserror::Error, Debug)]
+#[error("{0} file(s) failed verification")]
+pub struct VerifyFailed(pub usize);
+
 #[derive(thiserror::Error, Debug)]
 pub enum InstallerError {
     #[error("no binary found matching `{0}` {1} against archive `{2}` entries:\n\n{3}\nconsider using/modifying `--entry-glob` or `--entry-regex` filter to match one above")]
     EntryNotFound(String, &'static str, String, String),
 
+    #[error("signature verification failed: {0}")]
+    Signature(String),
+
+    #[error("`{0}` is {1}, not an executable\nconsider using/modifying `--entry-glob` or `--entry-regex` filter to match another entry, or `--ignore-arch` to install it anyway")]
+    NotExecutable(String, String),
+
+    #[error("`{0}` is {1}, not one for this {2} system\nconsider using/modifying `--asset-glob` or `--asset-regex` filter to match another asset, or `--ignore-arch` to install it anyway")]
+    TargetMismatch(String, String, String),
+
     #[error(transparent)]
     AnyHow(#[from] anyhow::Error), // source and Display delegate to anyhow::Error
 }
+
+/// Process exit codes, by a kind of failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// nothing to do, which isn't a failure
+    UpToDate = 0,
+    /// any failure not covered by other codes
+    Failure = 1,
+    /// some packages failed to update (see `UpdateFailed`)
+    UpdateFailed = 2,
+    /// no such repo, release, asset or archive entry
+    NotFound = 3,
+    /// several assets or archive entries matched
+    Ambiguous = 4,
+    /// a network failure, even after retries
+    Network = 5,
+    /// API rate limit exceeded
+    RateLimited = 6,
+    /// a file system failure
+    Io = 7,
+    /// an asset failed an integrity or a signature check,
+    /// or installed binaries failed verification (see `VerifyFailed`)
+    Integrity = 8,
+}
+
+/// Exit code for an error, by the first cause in its chain of a known kind.
+pub fn exit_code(e: &anyhow::Error) -> ExitCode {
+    e.chain()
+        .find_map(|cause| {
+            if let Some(e) = cause.downcast_ref::<GithubError>() {
+                match e {
+                    GithubError::ReleaseNotFound
+                    | GithubError::AssetNotFound
+                    | GithubError::AssetNoMatch => Some(ExitCode::NotFound),
+                    GithubError::AssetMultipleMatch(_) => Some(ExitCode::Ambiguous),
+                    GithubError::AlreadyUpToDate(_) => Some(ExitCode::UpToDate),
+                    GithubError::Integrity(_) => Some(ExitCode::Integrity),
+                    GithubError::RateLimited { .. } => Some(ExitCode::RateLimited),
+                    // look further down the chain
+                    GithubError::AnyHow(_) => None,
+                }
+            } else if let Some(e) = cause.downcast_ref::<InstallerError>() {
+                match e {
+                    InstallerError::EntryNotFound(..) => Some(ExitCode::NotFound),
+                    InstallerError::Signature(_) => Some(ExitCode::Integrity),
+                    InstallerError::NotExecutable(..) | InstallerError::TargetMismatch(..) => {
+                        Some(ExitCode::Failure)
+                    }
+                    InstallerError::AnyHow(_) => None,
+                }
+            } else if cause.is::<UpdateFailed>() {
+                Some(ExitCode::UpdateFailed)
+            } else if cause.is::<VerifyFailed>() {
+                Some(ExitCode::Integrity)
+            } else if cause.is::<reqwest::Error>() {
+                Some(ExitCode::Network)
+            } else if cause.is::<std::io::Error>() {
+                Some(ExitCode::Io)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(ExitCode::Failure)
+}
@@ -0,0 +1,820 @@
+use std::cmp;
+use std::collections::HashSet;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Local, TimeZone, Utc};
+use console::style;
+use futures_util::StreamExt;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use lazy_static::lazy_static;
+use reqwest::{header, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use url::Url;
+
+use super::cache;
+use super::error::GithubError;
+use super::gitea::Gitea;
+use super::github::{match_single_asset, Asset, AssetNameMatcher, GitHub, Release};
+use super::gitlab::GitLab;
+use super::package::Package;
+use super::plain_url::{PlainUrl, UrlSource};
+use super::util;
+
+type Result<T, E = GithubError> = std::result::Result<T, E>;
+
+const RETRY_BASE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+lazy_static! {
+    /// partial downloads in progress, to keep concurrent downloads of the same asset apart
+    static ref PARTIALS_IN_PROGRESS: Mutex<HashSet<PathBuf>> = Mutex::new(HashSet::new());
+}
+
+/// Kind of a *forge* hosting the releases of a package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    #[default]
+    #[clap(name = "github")]
+    GitHub,
+    #[clap(name = "gitlab")]
+    GitLab,
+    #[clap(name = "gitea", alias = "forgejo")]
+    Gitea,
+    /// a templated URL, not a forge (see `UrlSource`)
+    #[clap(skip)]
+    Url,
+}
+
+impl ForgeKind {
+    /// Forge kind of a well known public instance at `host`.
+    pub fn from_host(host: &str) -> Option<Self> {
+        match host {
+            "github.com" => Some(ForgeKind::GitHub),
+            "gitlab.com" => Some(ForgeKind::GitLab),
+            "codeberg.org" => Some(ForgeKind::Gitea),
+            _ => None,
+        }
+    }
+
+    /// API root of an instance at `host`, `None` when it is the default one.
+    pub fn api_url_for_host(&self, host: &str) -> Option<String> {
+        match (self, host) {
+            (ForgeKind::GitHub, "github.com")
+            | (ForgeKind::GitLab, "gitlab.com")
+            | (ForgeKind::Gitea, "codeberg.org") => None,
+            (ForgeKind::GitHub, host) => Some(format!("https://{}/api/v3", host)),
+            (ForgeKind::GitLab, host) => Some(format!("https://{}/api/v4", host)),
+            (ForgeKind::Gitea, host) => Some(format!("https://{}/api/v1", host)),
+            (ForgeKind::Url, _) => None,
+        }
+    }
+
+    /// Web URL of an instance with `api_url` API root (or of the default one),
+    /// used for display purposes.
+    pub fn web_url(&self, api_url: Option<&str>) -> String {
+        match api_url.and_then(|api_url| Url::parse(api_url).ok()) {
+            Some(api_url) => api_url.origin().ascii_serialization(),
+            None => match self {
+                ForgeKind::GitHub => "https://github.com".to_owned(),
+                ForgeKind::GitLab => "https://gitlab.com".to_owned(),
+                ForgeKind::Gitea => "https://codeberg.org".to_owned(),
+                ForgeKind::Url => String::new(),
+            },
+        }
+    }
+}
+
+/// A source of releases and their assets.
+#[async_trait]
+pub trait Forge: Send + Sync {
+    /// Find a `Release` matching provided parameters.
+    async fn find_new(
+        &self,
+        user: &str,
+        repo: &str,
+        requested: &str,
+        asset_glob: Option<&str>,
+        asset_re: Option<&str>,
+    ) -> Result<Release>;
+
+    /// Download an `Asset` into `temp_dir`, returning a path to the downloaded file.
+    async fn download(
+        &self,
+        user: &str,
+        repo: &str,
+        asset: &Asset,
+        temp_dir: &TempDir,
+    ) -> Result<PathBuf>;
+
+    /// Whether releases may come with checksum files, so that a missing one is worth
+    /// a warning.
+    fn publishes_checksums(&self) -> bool {
+        true
+    }
+
+    /// Like `find_new`, but conditional on a release endpoint response having changed
+    /// since the one with `validators`, failing with `GithubError::AlreadyUpToDate`
+    /// otherwise. Forges that don't support conditional requests ignore `validators`.
+    async fn find_changed(
+        &self,
+        user: &str,
+        repo: &str,
+        requested: &str,
+        asset_glob: Option<&str>,
+        asset_re: Option<&str>,
+        _validators: Option<&Validators>,
+    ) -> Result<Release> {
+        self.find_new(user, repo, requested, asset_glob, asset_re)
+            .await
+    }
+
+    /// Find a `Release` matching provided `Package`, that is newer than
+    /// the one specified in `Package`.
+    async fn find_existing(&self, package: &Package) -> Result<Release> {
+        let release = self
+            .find_changed(
+                &package.user,
+                &package.repo,
+                &package.requested,
+                package.asset_glob.as_deref(),
+                package.asset_re.as_deref(),
+                package.validators.as_ref(),
+            )
+            .await?;
+
+        // we want to compare release's `published_at` date to
+        // what we have on record. If it's the same as ours, skip it.
+        // NB: Strict comparison for equality should be faster and enough.
+        if release.tag_name == package.tag && release.published_at == package.timestamp {
+            Err(GithubError::AlreadyUpToDate(release.validators))
+        } else {
+            Ok(release)
+        }
+    }
+}
+
+/// HTTP cache validators of a response, used to make a conditional request
+/// for the same resource.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Validators {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// Validators of `resp`, `None` when it has none.
+    pub fn from_response(resp: &Response) -> Option<Self> {
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        };
+
+        let validators = Self {
+            etag: header(header::ETAG),
+            last_modified: header(header::LAST_MODIFIED),
+        };
+        (validators.etag.is_some() || validators.last_modified.is_some()).then_some(validators)
+    }
+
+    /// Make `req` conditional on the resource having changed since these validators.
+    pub fn apply(&self, req: RequestBuilder) -> RequestBuilder {
+        match (&self.etag, &self.last_modified) {
+            (Some(etag), _) => req.header(header::IF_NONE_MATCH, etag),
+            (None, Some(last_modified)) => req.header(header::IF_MODIFIED_SINCE, last_modified),
+            (None, None) => req,
+        }
+    }
+}
+
+/// Network behavior of a `Forge`, shared by all forge kinds.
+#[derive(Debug, Clone)]
+pub struct ForgeOptions {
+    /// wait for a rate limit to reset, instead of failing with `GithubError::RateLimited`
+    pub wait_rate_limit: bool,
+    /// report remaining rate limit quota, and retries
+    pub verbose: bool,
+    /// number of times to retry a request failed due to a transient error
+    pub retries: u32,
+    /// upper bound of a delay between retries
+    pub retry_max_delay: std::time::Duration,
+    /// upper bound of release list pages to look through for a *semver* match
+    pub max_pages: usize,
+    /// where to display download progress, when downloading several assets at once
+    pub progress: Option<MultiProgress>,
+}
+
+impl Default for ForgeOptions {
+    fn default() -> Self {
+        Self {
+            wait_rate_limit: false,
+            verbose: false,
+            retries: 3,
+            retry_max_delay: std::time::Duration::from_secs(30),
+            max_pages: 10,
+            progress: None,
+        }
+    }
+}
+
+impl ForgeOptions {
+    /// A jittered exponential backoff delay before a retry `attempt` (starting at 1).
+    fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let delay = RETRY_BASE_DELAY
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.retry_max_delay);
+        // "equal jitter": somewhere between a half and a full delay
+        delay / 2 + delay.mul_f64(fastrand::f64() / 2.0)
+    }
+
+    async fn wait_before_retry(
+        &self,
+        attempt: u32,
+        what: &str,
+        reason: &(dyn std::fmt::Display + Sync),
+    ) {
+        let delay = self.backoff(attempt);
+        if self.verbose {
+            self.message(&format!(
+                "{}: {}, retrying in {:.1}s ({}/{})",
+                what,
+                reason,
+                delay.as_secs_f64(),
+                attempt,
+                self.retries
+            ));
+        }
+        tokio::time::sleep(delay).await;
+    }
+
+    /// Print a message to stderr, without garbling progress bars, if any are displayed.
+    pub fn message(&self, msg: &str) {
+        match &self.progress {
+            Some(progress) => progress.suspend(|| eprintln!("{}", msg)),
+            None => eprintln!("{}", msg),
+        }
+    }
+
+    /// Print a warning, like `message`.
+    pub fn warn(&self, msg: &str) {
+        self.message(&format!("{} {}", style("warning:").yellow(), msg));
+    }
+}
+
+/// Create a `Forge` of a given kind. `api_url` overrides the default API root,
+/// `url_source` is required by, and only used for `ForgeKind::Url`.
+pub fn create(
+    kind: ForgeKind,
+    token: Option<&String>,
+    api_url: Option<&str>,
+    url_source: Option<&UrlSource>,
+    opts: &ForgeOptions,
+) -> Result<Box<dyn Forge>> {
+    let opts = opts.clone();
+    Ok(match kind {
+        ForgeKind::GitHub => Box::new(GitHub::create(token, api_url, opts)),
+        ForgeKind::GitLab => Box::new(GitLab::create(token, api_url, opts)),
+        ForgeKind::Gitea => Box::new(Gitea::create(token, api_url, opts)),
+        ForgeKind::Url => {
+            let url_source = url_source
+                .cloned()
+                .ok_or_else(|| anyhow!("a URL package is missing its URL template"))?;
+            Box::new(PlainUrl::create(url_source, opts))
+        }
+    })
+}
+
+/// Send a request, handling rate limiting and retrying transient failures
+/// as specified by `opts`. Must only be used with idempotent requests.
+/// `what` describes the request for error reporting.
+pub async fn send(
+    req: RequestBuilder,
+    what: &'static str,
+    opts: &ForgeOptions,
+) -> Result<Response> {
+    let mut attempt = 0;
+    loop {
+        let resp = match req.try_clone().context("cloning a request")?.send().await {
+            Err(e) if attempt < opts.retries && !e.is_builder() => {
+                attempt += 1;
+                opts.wait_before_retry(attempt, what, &e).await;
+                continue;
+            }
+            resp => resp.context(what)?,
+        };
+
+        if is_transient(resp.status()) && attempt < opts.retries {
+            attempt += 1;
+            opts.wait_before_retry(attempt, what, &resp.status()).await;
+            continue;
+        }
+
+        if opts.verbose {
+            if let Some(remaining) = rate_limit_header(&resp, "remaining") {
+                opts.message(&format!("{} API requests remaining", remaining));
+            }
+        }
+
+        match rate_limit_reset(&resp) {
+            Some(reset_at) if opts.wait_rate_limit => {
+                opts.message(&format!(
+                    "rate limited, waiting until {}",
+                    reset_at.with_timezone(&Local).format("%H:%M:%S")
+                ));
+                let wait = (reset_at - Utc::now()).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+            }
+            Some(reset_at) => return Err(GithubError::RateLimited { reset_at }),
+            None => return Ok(resp),
+        }
+    }
+}
+
+/// A URL of the next page of a paginated response, as per its `Link` header, i.e.
+/// `Link: <https://api.github.com/...?page=2>; rel="next", <...?page=5>; rel="last"`.
+pub fn next_page_url(resp: &Response) -> Option<String> {
+    resp.headers()
+        .get_all(header::LINK)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|link| {
+            let (url, params) = link.trim().split_once(';')?;
+            params
+                .split(';')
+                .any(|param| {
+                    matches!(
+                        param.trim().split_once('='),
+                        Some(("rel", rel)) if rel.trim_matches('"').split(' ').any(|rel| rel == "next")
+                    )
+                })
+                .then(|| url.trim().trim_start_matches('<').trim_end_matches('>').to_owned())
+        })
+}
+
+/// A page of releases, listed newest first, fetched in a *semver* search.
+pub struct ReleasesPage {
+    pub releases: Vec<Release>,
+    /// a URL of the next page, as per a `Link` header (see `next_page_url`)
+    pub next_page_url: Option<String>,
+    /// validators of a response, to record for the next conditional request
+    pub validators: Option<Validators>,
+}
+
+/// Find the newest release matching a *semver* `requested`, that has a single asset matched
+/// by `asset_matcher`. Pages of releases are fetched by `fetch_page`, given a page URL and
+/// number, starting from `first_page_url`, up to `opts.max_pages` of them.
+///
+/// New releases show up on the first page, so validators of its response are recorded
+/// with a release found on it, for a conditional request of the list to tell nothing
+/// changed. Ones found on later pages are recorded without validators, since an edit
+/// of them (i.e. a re-uploaded asset) doesn't change the first page.
+pub async fn find_semver_release<F, Fut>(
+    first_page_url: String,
+    requested: &str,
+    asset_matcher: &AssetNameMatcher,
+    opts: &ForgeOptions,
+    mut fetch_page: F,
+) -> Result<Release>
+where
+    F: FnMut(String, usize) -> Fut,
+    Fut: Future<Output = Result<ReleasesPage>>,
+{
+    let mut curr_page: usize = 1;
+    let mut page_url = first_page_url;
+
+    loop {
+        let page = fetch_page(page_url.clone(), curr_page).await?;
+
+        let below = util::below_semver(
+            page.releases
+                .iter()
+                .map(|release| release.tag_name.as_str()),
+            requested,
+        );
+
+        if let Some(mut release) = page
+            .releases
+            .into_iter()
+            .find(|release| util::matches_semver(&release.tag_name, requested))
+        {
+            release.validators = page.validators.filter(|_| curr_page == 1);
+            break match match_single_asset(release, asset_matcher) {
+                Err(GithubError::AssetNoMatch) => Err(GithubError::ReleaseNotFound),
+                res => res,
+            };
+        }
+
+        // releases are listed newest first, so there is no point in going any further
+        if below {
+            break Err(GithubError::ReleaseNotFound);
+        }
+
+        match page.next_page_url {
+            None => break Err(GithubError::ReleaseNotFound),
+            Some(_) if curr_page >= opts.max_pages => {
+                break Err(max_pages_reached(requested, opts))
+            }
+            Some(next_page_url) => {
+                page_url = next_page_url;
+                curr_page += 1;
+            }
+        }
+    }
+}
+
+/// Make sure a release request of `url` got a successful response.
+pub fn check_release_status(resp: &Response, url: &str) -> Result<()> {
+    match resp.status() {
+        StatusCode::OK => Ok(()),
+        StatusCode::NOT_FOUND => Err(GithubError::ReleaseNotFound),
+        status => Err(GithubError::AnyHow(anyhow!(
+            "getting: {} ({})",
+            url,
+            status
+        ))),
+    }
+}
+
+/// An error of giving up on a search for a `requested` *semver* release
+/// after looking through `opts.max_pages` pages of releases.
+fn max_pages_reached(requested: &str, opts: &ForgeOptions) -> GithubError {
+    GithubError::AnyHow(anyhow!(
+        "no release matching `{}` found within {} pages of releases\nconsider raising `--max-pages`",
+        requested,
+        opts.max_pages
+    ))
+}
+
+/// Server errors, that are worth retrying.
+fn is_transient(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// A `X-RateLimit-<name>` (GitHub, Gitea) or `RateLimit-<name>` (GitLab) header value.
+fn rate_limit_header(resp: &Response, name: &str) -> Option<i64> {
+    ["x-ratelimit-", "ratelimit-"].iter().find_map(|prefix| {
+        resp.headers()
+            .get(format!("{}{}", prefix, name))
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+    })
+}
+
+/// When a response is rate limited, returns the time at which the limit resets.
+fn rate_limit_reset(resp: &Response) -> Option<DateTime<Utc>> {
+    let status = resp.status();
+    if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+
+    // secondary rate limits only come with a `Retry-After` (in seconds)
+    let retry_after = resp
+        .headers()
+        .get(header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .map(|secs| Utc::now() + Duration::seconds(secs));
+    let reset_at =
+        || rate_limit_header(resp, "reset").and_then(|epoch| Utc.timestamp_opt(epoch, 0).single());
+
+    if retry_after.is_some() {
+        retry_after
+    } else if rate_limit_header(resp, "remaining") == Some(0) {
+        reset_at()
+    } else if status == StatusCode::TOO_MANY_REQUESTS {
+        reset_at().or_else(|| Some(Utc::now() + Duration::seconds(60)))
+    } else {
+        // a 403 which isn't about rate limiting
+        None
+    }
+}
+
+/// Download an `asset` into `temp_dir`, returning a path to the downloaded file.
+/// When the asset's size is known, it is verified.
+///
+/// The asset is first downloaded into a persistent cache directory, so a transfer
+/// interrupted by a network error is resumed with a `Range` request, rather than
+/// restarted. This happens within the retries specified by `opts`, as well as across
+/// runs. A partial download is only resumed when the server identifies its
+/// version (`ETag` or `Last-Modified`), and that version hasn't changed since.
+pub async fn download(
+    req: RequestBuilder,
+    asset: &Asset,
+    temp_dir: &TempDir,
+    opts: &ForgeOptions,
+) -> Result<PathBuf> {
+    let url = req
+        .try_clone()
+        .context("cloning a request")?
+        .build()
+        .context("building a request")?
+        .url()
+        .to_string();
+    let partial = Partial::new(&url, &asset.name, temp_dir)?;
+
+    let mut attempt = 0;
+    loop {
+        let mut req = req.try_clone().context("cloning a request")?;
+        let resume = partial.resumable();
+        if let Some((offset, meta)) = &resume {
+            req = req.header(header::RANGE, format!("bytes={}-", offset));
+            let validators = &meta.validators;
+            if let Some(validator) = validators
+                .etag
+                .as_ref()
+                .or(validators.last_modified.as_ref())
+            {
+                req = req.header(header::IF_RANGE, validator);
+            }
+        }
+
+        let resp = send(req, "fetching an asset", opts).await?;
+
+        let offset = match (resp.status(), resume) {
+            (StatusCode::NOT_FOUND, _) => return Err(GithubError::AssetNotFound),
+            (StatusCode::PARTIAL_CONTENT, Some((offset, meta))) => {
+                if !meta.matches(&resp, offset) {
+                    // the asset changed, or the server got the range wrong
+                    partial.discard();
+                    continue;
+                }
+                offset
+            }
+            (StatusCode::RANGE_NOT_SATISFIABLE, Some(_)) => {
+                partial.discard();
+                continue;
+            }
+            (StatusCode::OK, _) => {
+                // either a fresh download, or the asset changed since a partial one
+                partial.save_meta(PartialMeta::from_response(&url, &resp).as_ref());
+                0
+            }
+            (status, _) => {
+                let mut msg = format!("getting: {} ({})", resp.url(), status);
+                if let Ok(txt) = resp.text().await {
+                    msg.push('\n');
+                    msg.push_str(&txt);
+                }
+                return Err(GithubError::AnyHow(anyhow!(msg)));
+            }
+        };
+
+        match write_response(resp, &partial.path, offset, opts.progress.as_ref()).await {
+            // only network errors are worth retrying, not the file system ones
+            Err(e) if attempt < opts.retries && e.downcast_ref::<reqwest::Error>().is_some() => {
+                attempt += 1;
+                opts.wait_before_retry(attempt, "downloading an asset", &e)
+                    .await;
+            }
+            res => {
+                res?;
+                let path = partial.complete(temp_dir)?;
+
+                let size = fs::metadata(&path)
+                    .context("reading a download size")?
+                    .len();
+                if asset.size != 0 && asset.size != size {
+                    return Err(GithubError::Integrity(format!(
+                        "{} is {} bytes, expected {}",
+                        &asset.name, size, asset.size
+                    )));
+                }
+
+                return Ok(path);
+            }
+        }
+    }
+}
+
+/// A partial download of an asset, kept in the cache directory,
+/// along with the metadata identifying the asset's version.
+struct Partial {
+    asset_name: String,
+    path: PathBuf,
+    meta_path: PathBuf,
+    /// whether `path` is claimed in `PARTIALS_IN_PROGRESS`
+    claimed: bool,
+}
+
+/// Identifies the version of a partially downloaded asset.
+#[derive(Debug, Deserialize, Serialize)]
+struct PartialMeta {
+    url: String,
+    #[serde(flatten)]
+    validators: Validators,
+    /// a total size of the asset, when known
+    size: Option<u64>,
+}
+
+impl Partial {
+    /// A partial download of `asset_name`, located by its request `url`. While
+    /// the same asset is already being downloaded, this one goes into `temp_dir`,
+    /// and can only be resumed within the retries of this run.
+    fn new(url: &str, asset_name: &str, temp_dir: &TempDir) -> anyhow::Result<Self> {
+        // a stable digest, so that other builds of `gitrel` resume it too
+        let url_digest: String = Sha256::digest(url.as_bytes())[..8]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        let cache_dir = util::cache_dir(cache::PARTIAL_DIR)?;
+        let file_name = format!("{}-{}", url_digest, asset_name);
+
+        let claimed = PARTIALS_IN_PROGRESS
+            .lock()
+            .unwrap()
+            .insert(cache_dir.join(&file_name));
+        let dir = if claimed {
+            cache_dir.as_path()
+        } else {
+            temp_dir.path()
+        };
+
+        Ok(Self {
+            asset_name: asset_name.to_owned(),
+            path: dir.join(&file_name),
+            meta_path: dir.join(format!("{}.json", file_name)),
+            claimed,
+        })
+    }
+
+    /// The size of a partial download and its metadata, when it can be resumed.
+    fn resumable(&self) -> Option<(u64, PartialMeta)> {
+        let meta = fs::read_to_string(&self.meta_path).ok()?;
+        let meta: PartialMeta = serde_json::from_str(&meta).ok()?;
+        let len = fs::metadata(&self.path).ok()?.len();
+
+        let resumable = len > 0 && meta.size.is_none_or(|size| len < size);
+        resumable.then_some((len, meta))
+    }
+
+    /// Record the metadata of a fresh download. Without a validator, there's
+    /// no way to tell whether the asset changed, so nothing is recorded.
+    fn save_meta(&self, meta: Option<&PartialMeta>) {
+        match meta.and_then(|meta| serde_json::to_string(meta).ok()) {
+            // a failure here only means the download won't be resumable
+            Some(meta) => {
+                let _ = fs::write(&self.meta_path, meta);
+            }
+            None => {
+                let _ = fs::remove_file(&self.meta_path);
+            }
+        }
+    }
+
+    fn discard(&self) {
+        let _ = fs::remove_file(&self.path);
+        let _ = fs::remove_file(&self.meta_path);
+    }
+
+    /// Move a completed download into `temp_dir`.
+    fn complete(&self, temp_dir: &TempDir) -> anyhow::Result<PathBuf> {
+        let temp_file_name = temp_dir.path().join(&self.asset_name);
+
+        // the cache and temp directories may well be on different file systems
+        if fs::rename(&self.path, &temp_file_name).is_err() {
+            fs::copy(&self.path, &temp_file_name).context(format!(
+                "copying a download to a temp file: {:?}",
+                temp_file_name.as_path(),
+            ))?;
+        }
+        self.discard();
+
+        Ok(temp_file_name)
+    }
+}
+
+impl Drop for Partial {
+    fn drop(&mut self) {
+        if self.claimed {
+            PARTIALS_IN_PROGRESS.lock().unwrap().remove(&self.path);
+        }
+    }
+}
+
+impl PartialMeta {
+    fn from_response(url: &str, resp: &Response) -> Option<Self> {
+        Some(Self {
+            url: url.to_owned(),
+            validators: Validators::from_response(resp)?,
+            size: resp.content_length(),
+        })
+    }
+
+    /// Whether a `206 Partial Content` response continues this partial download
+    /// from `offset`, rather than serving a changed asset or some other range.
+    fn matches(&self, resp: &Response, offset: u64) -> bool {
+        let etag = resp
+            .headers()
+            .get(header::ETAG)
+            .and_then(|value| value.to_str().ok());
+        if let (Some(etag), Some(expected)) = (etag, &self.validators.etag) {
+            if etag != expected {
+                return false;
+            }
+        }
+
+        // i.e. `Content-Range: bytes 1024-4095/4096`
+        let range = resp
+            .headers()
+            .get(header::CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("bytes "))
+            .and_then(|value| value.split_once('/'))
+            .and_then(|(range, total)| {
+                let (start, _) = range.split_once('-')?;
+                Some((start.parse::<u64>().ok()?, total.parse::<u64>().ok()))
+            });
+
+        match range {
+            Some((start, total)) => {
+                start == offset && (self.size.is_none() || total.is_none() || total == self.size)
+            }
+            None => false,
+        }
+    }
+}
+
+/// Stream a successful asset download response into `path`, displaying progress.
+/// With a non-zero `offset`, a response continues a partial download, and is appended.
+async fn write_response(
+    resp: Response,
+    path: &Path,
+    offset: u64,
+    progress: Option<&MultiProgress>,
+) -> anyhow::Result<()> {
+    let tot_size = resp.content_length().map(|len| offset + len);
+
+    let pb = match tot_size {
+        Some(tot_size) => ProgressBar::new(tot_size).with_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+                .unwrap()
+                .progress_chars("##-")
+        ),
+        // some forges serve asset links from hosts that don't report a size
+        None => ProgressBar::new_spinner().with_style(
+            ProgressStyle::default_spinner()
+                .template("[{elapsed_precise}] {spinner:.cyan} {bytes} ({bytes_per_sec})")
+                .unwrap()
+        ),
+    };
+
+    let pb = match progress {
+        Some(progress) => progress.add(pb),
+        None => pb,
+    };
+
+    let mut downloaded: u64 = offset;
+    pb.set_position(downloaded);
+    let mut stream = resp.bytes_stream();
+
+    let mut file = if offset > 0 {
+        OpenOptions::new().append(true).open(path).await
+    } else {
+        File::create(path).await
+    }
+    .context(format!("opening a download file: {:?}", path))?;
+
+    while let Some(item) = stream.next().await {
+        let chunk = match item.context("retrieving a next chunk") {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                // keep what's been downloaded so far, for a retry to resume from
+                let _ = file.flush().await;
+                pb.finish_and_clear();
+                return Err(e);
+            }
+        };
+        file.write_all(&chunk)
+            .await
+            .context("writing a chunk to download file")?;
+        let new = downloaded + (chunk.len() as u64);
+        downloaded = tot_size.map_or(new, |tot_size| cmp::min(new, tot_size));
+        pb.set_position(downloaded);
+    }
+
+    file.flush().await.context("flushing download file")?;
+    pb.finish_and_clear();
+
+    Ok(())
+}
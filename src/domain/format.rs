@@ -0,0 +1,409 @@
+use std::fmt::{self, Write};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use bzip2::read::BzDecoder;
+use flate2::read::GzDecoder;
+use lz4_flex::frame::FrameDecoder;
+use xz::read::XzDecoder;
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+use super::util::{self, ArchiveKind, TarKind};
+
+/// Bytes of a file header looked at, enough for a tar header up to its `ustar` magic.
+const HEADER_LEN: usize = 262;
+/// Offset of the `ustar` magic in a tar header.
+const USTAR_OFFSET: usize = 257;
+/// Bytes of an executable header looked at, enough for a PE header after a DOS stub,
+/// or for a universal Mach-O header of a few architectures.
+const EXECUTABLE_HEADER_LEN: usize = 1024;
+
+/// Executables of a format and an architecture, that can be run on a host of another one
+/// by the OS: (format, executable architecture, host architecture).
+const COMPATIBLE_ARCHS: [(ExecutableKind, Arch, Arch); 4] = [
+    // Rosetta 2
+    (ExecutableKind::MachO, Arch::X86_64, Arch::Aarch64),
+    // WOW64
+    (ExecutableKind::Pe, Arch::X86, Arch::X86_64),
+    (ExecutableKind::Pe, Arch::X86, Arch::Aarch64),
+    // x64 emulation of Windows 11 on Arm
+    (ExecutableKind::Pe, Arch::X86_64, Arch::Aarch64),
+];
+
+/// A format of an executable file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutableKind {
+    Elf,
+    MachO,
+    Pe,
+    /// i.e. a shell script, starting with `#!`
+    Script,
+}
+
+impl ExecutableKind {
+    /// Whether executables of this format can be run on the current OS.
+    pub fn is_native(self) -> bool {
+        match self {
+            ExecutableKind::Elf => cfg!(all(unix, not(target_os = "macos"))),
+            ExecutableKind::MachO => cfg!(target_os = "macos"),
+            ExecutableKind::Pe => cfg!(target_os = "windows"),
+            ExecutableKind::Script => cfg!(unix),
+        }
+    }
+
+    /// How a format is referred to in messages.
+    pub fn describe(self) -> &'static str {
+        match self {
+            ExecutableKind::Elf => "an ELF executable",
+            ExecutableKind::MachO => "a Mach-O executable",
+            ExecutableKind::Pe => "a PE executable",
+            ExecutableKind::Script => "a script",
+        }
+    }
+}
+
+/// An architecture an executable is built for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arch {
+    X86,
+    X86_64,
+    Arm,
+    Aarch64,
+    /// a machine type of a format, and a bitness
+    Other(u32, u8),
+}
+
+impl Arch {
+    /// An architecture of the current platform.
+    pub fn host() -> Arch {
+        if cfg!(target_arch = "x86_64") {
+            Arch::X86_64
+        } else if cfg!(target_arch = "aarch64") {
+            Arch::Aarch64
+        } else if cfg!(target_arch = "arm") {
+            Arch::Arm
+        } else {
+            Arch::X86
+        }
+    }
+}
+
+impl fmt::Display for Arch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arch::X86 => f.write_str("x86"),
+            Arch::X86_64 => f.write_str("x86_64"),
+            Arch::Arm => f.write_str("arm"),
+            Arch::Aarch64 => f.write_str("aarch64"),
+            Arch::Other(machine, bits) => write!(f, "{}-bit machine {:#x}", bits, machine),
+        }
+    }
+}
+
+/// A header of an executable file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Executable {
+    pub kind: ExecutableKind,
+    /// architectures it's built for, several ones of a universal Mach-O binary, and none
+    /// of a script, or when a header is cut short
+    pub archs: Vec<Arch>,
+    /// OS ABI of an ELF executable
+    pub os_abi: Option<u8>,
+}
+
+impl Executable {
+    /// Why it can't be run on the current platform, i.e. `an ELF executable for aarch64`,
+    /// or `None` if it can.
+    pub fn mismatch(&self) -> Option<String> {
+        let native_os = self.kind.is_native()
+            && self.os_abi.is_none_or(|os_abi| match os_abi_name(os_abi) {
+                // Linux binaries are mostly marked as System V ones
+                None | Some("Linux") => cfg!(target_os = "linux"),
+                Some(_) => false,
+            });
+        let native_arch = self.archs.is_empty()
+            || self
+                .archs
+                .iter()
+                .any(|&arch| runs_on(self.kind, arch, Arch::host()));
+        if native_os && native_arch {
+            return None;
+        }
+
+        let mut mismatch = self.kind.describe().to_owned();
+        if !self.archs.is_empty() {
+            let archs: Vec<_> = self.archs.iter().map(Arch::to_string).collect();
+            let _ = write!(mismatch, " for {}", archs.join(" and "));
+        }
+        if let Some(os) = self.os_abi.and_then(os_abi_name) {
+            let _ = write!(mismatch, " ({})", os);
+        }
+        Some(mismatch)
+    }
+}
+
+/// Whether an executable of a format `kind`, built for `arch`, can be run on a `host`.
+fn runs_on(kind: ExecutableKind, arch: Arch, host: Arch) -> bool {
+    arch == host || COMPATIBLE_ARCHS.contains(&(kind, arch, host))
+}
+
+/// A kind of archive of a downloaded asset at `asset_path`, told by its magic bytes.
+/// The asset name is only looked at, when they're not of a known format, or to tell
+/// a compressed tarball without the `ustar` magic (i.e. a V7 one) apart.
+pub fn archive_kind(asset_path: &Path, asset_name: &str) -> Result<ArchiveKind> {
+    let header = read_header(asset_path, HEADER_LEN)?;
+    let by_name = util::archive_kind(asset_name);
+
+    let (archive_kind, tar_kind) = match header.as_slice() {
+        [b'P', b'K', 3, 4, ..] | [b'P', b'K', 5, 6, ..] | [b'P', b'K', 7, 8, ..] => {
+            return Ok(ArchiveKind::Zip)
+        }
+        [b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c, ..] => return Ok(ArchiveKind::SevenZip),
+        [0x1f, 0x8b, ..] => (ArchiveKind::GZip, TarKind::GZip),
+        [b'B', b'Z', b'h', ..] => (ArchiveKind::BZip, TarKind::BZip),
+        [0xfd, b'7', b'z', b'X', b'Z', 0, ..] => (ArchiveKind::XZ, TarKind::XZ),
+        [0x28, 0xb5, 0x2f, 0xfd, ..] => (ArchiveKind::Zstd, TarKind::Zstd),
+        [0x04, 0x22, 0x4d, 0x18, ..] => (ArchiveKind::Lz4, TarKind::Lz4),
+        header if is_tar_header(header) => return Ok(ArchiveKind::Tar(TarKind::Uncompressed)),
+        header if executable_format(header).is_some() => return Ok(ArchiveKind::Uncompressed),
+        _ => return Ok(by_name),
+    };
+
+    // a compressed tarball, or a single compressed file
+    let file = File::open(asset_path).context("opening downloaded file")?;
+    let decompressed = match tar_kind {
+        TarKind::GZip => read_start(GzDecoder::new(file), HEADER_LEN),
+        TarKind::BZip => read_start(BzDecoder::new(file), HEADER_LEN),
+        TarKind::XZ => read_start(XzDecoder::new(file), HEADER_LEN),
+        TarKind::Zstd => ZstdDecoder::new(file).and_then(|reader| read_start(reader, HEADER_LEN)),
+        TarKind::Lz4 => read_start(FrameDecoder::new(file), HEADER_LEN),
+        TarKind::Uncompressed => unreachable!(),
+    }
+    .unwrap_or_default();
+
+    if is_tar_header(&decompressed) || by_name == ArchiveKind::Tar(tar_kind) {
+        Ok(ArchiveKind::Tar(tar_kind))
+    } else {
+        Ok(archive_kind)
+    }
+}
+
+/// A header of an executable file at `path`, or `None` if it's not one.
+pub fn executable(path: &Path) -> Result<Option<Executable>> {
+    let header = read_header(path, EXECUTABLE_HEADER_LEN)?;
+    let kind = match executable_format(&header) {
+        Some(kind) => kind,
+        None => return Ok(None),
+    };
+
+    let (archs, os_abi) = match kind {
+        ExecutableKind::Elf => (
+            elf_arch(&header).into_iter().collect(),
+            header.get(7).copied(),
+        ),
+        ExecutableKind::MachO => (mach_o_archs(&header), None),
+        ExecutableKind::Pe => (pe_arch(&header).into_iter().collect(), None),
+        ExecutableKind::Script => (Vec::new(), None),
+    };
+    Ok(Some(Executable {
+        kind,
+        archs,
+        os_abi,
+    }))
+}
+
+/// A format of an executable file starting with `header`, or `None` if it's not one.
+fn executable_format(header: &[u8]) -> Option<ExecutableKind> {
+    match header {
+        [0x7f, b'E', b'L', b'F', ..] => Some(ExecutableKind::Elf),
+        // 32 and 64-bit, either endianness, and universal binaries
+        [0xfe, 0xed, 0xfa, 0xce | 0xcf, ..]
+        | [0xce | 0xcf, 0xfa, 0xed, 0xfe, ..]
+        | [0xca, 0xfe, 0xba, 0xbe, ..] => Some(ExecutableKind::MachO),
+        [b'M', b'Z', ..] => Some(ExecutableKind::Pe),
+        [b'#', b'!', ..] => Some(ExecutableKind::Script),
+        _ => None,
+    }
+}
+
+fn elf_arch(header: &[u8]) -> Option<Arch> {
+    let bits = match header.get(4)? {
+        1 => 32,
+        2 => 64,
+        _ => return None,
+    };
+    let machine = match header.get(5)? {
+        1 => u16::from_le_bytes(header.get(18..20)?.try_into().ok()?),
+        _ => u16::from_be_bytes(header.get(18..20)?.try_into().ok()?),
+    };
+    let arch = match (machine, bits) {
+        (3, 32) => Arch::X86,
+        (62, 64) => Arch::X86_64,
+        (40, 32) => Arch::Arm,
+        (183, 64) => Arch::Aarch64,
+        (machine, bits) => Arch::Other(machine.into(), bits),
+    };
+    Some(arch)
+}
+
+fn mach_o_archs(header: &[u8]) -> Vec<Arch> {
+    let u32_at = |offset: usize, big_endian: bool| {
+        let bytes = header.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+    let arch = |cpu_type: u32| match cpu_type {
+        7 => Arch::X86,
+        0x0100_0007 => Arch::X86_64,
+        12 => Arch::Arm,
+        0x0100_000c => Arch::Aarch64,
+        cpu_type if cpu_type & 0x0100_0000 != 0 => Arch::Other(cpu_type, 64),
+        cpu_type => Arch::Other(cpu_type, 32),
+    };
+
+    match header {
+        // a universal binary, of `fat_arch` entries of 20 bytes
+        [0xca, 0xfe, 0xba, 0xbe, ..] => {
+            let count = u32_at(4, true).unwrap_or_default() as usize;
+            (0..count)
+                .map_while(|i| u32_at(8 + i * 20, true))
+                .map(arch)
+                .collect()
+        }
+        [0xfe, ..] => u32_at(4, true).map(arch).into_iter().collect(),
+        _ => u32_at(4, false).map(arch).into_iter().collect(),
+    }
+}
+
+fn pe_arch(header: &[u8]) -> Option<Arch> {
+    let pe_offset = u32::from_le_bytes(header.get(0x3c..0x40)?.try_into().ok()?) as usize;
+    if header.get(pe_offset..pe_offset + 4)? != b"PE\0\0" {
+        return None;
+    }
+    let machine = u16::from_le_bytes(header.get(pe_offset + 4..pe_offset + 6)?.try_into().ok()?);
+    let arch = match machine {
+        0x014c => Arch::X86,
+        0x8664 => Arch::X86_64,
+        0x01c4 => Arch::Arm,
+        0xaa64 => Arch::Aarch64,
+        machine => {
+            // told by a magic of an optional header, following a 20 bytes COFF one
+            let magic = header.get(pe_offset + 24..pe_offset + 26);
+            let bits = if magic == Some(&[0x0b, 0x02]) { 64 } else { 32 };
+            Arch::Other(machine.into(), bits)
+        }
+    };
+    Some(arch)
+}
+
+/// A name of an ELF OS ABI, or `None` of a generic System V one.
+fn os_abi_name(os_abi: u8) -> Option<&'static str> {
+    match os_abi {
+        0 => None,
+        1 => Some("HP-UX"),
+        2 => Some("NetBSD"),
+        3 => Some("Linux"),
+        6 => Some("Solaris"),
+        9 => Some("FreeBSD"),
+        12 => Some("OpenBSD"),
+        _ => Some("unknown OS ABI"),
+    }
+}
+
+fn is_tar_header(header: &[u8]) -> bool {
+    header.get(USTAR_OFFSET..HEADER_LEN) == Some(b"ustar")
+}
+
+fn read_header(path: &Path, len: usize) -> Result<Vec<u8>> {
+    let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    read_start(BufReader::new(file), len).with_context(|| format!("reading {}", path.display()))
+}
+
+fn read_start(reader: impl Read, len: usize) -> io::Result<Vec<u8>> {
+    let mut header = Vec::with_capacity(len);
+    reader.take(len as u64).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    #[test]
+    fn sniffed_archive_kinds() {
+        let mut tar_header = vec![0; 512];
+        tar_header[USTAR_OFFSET..HEADER_LEN].copy_from_slice(b"ustar");
+        let mut gzipped_tar = GzEncoder::new(Vec::new(), Compression::default());
+        gzipped_tar.write_all(&tar_header).unwrap();
+        let gzipped_tar = gzipped_tar.finish().unwrap();
+
+        let kind = |contents: &[u8], asset_name: &str| {
+            let mut file = tempfile::NamedTempFile::new().unwrap();
+            file.write_all(contents).unwrap();
+            archive_kind(file.path(), asset_name).unwrap()
+        };
+
+        // names with dots in them are told apart
+        assert_eq!(
+            kind(&gzipped_tar, "tool-v1.2.3-linux"),
+            ArchiveKind::Tar(TarKind::GZip)
+        );
+        assert_eq!(
+            kind(&tar_header, "tool.gz"),
+            ArchiveKind::Tar(TarKind::Uncompressed)
+        );
+        assert_eq!(
+            kind(b"\x7fELF\x02\x01\x01", "tool.v1.tar.gz"),
+            ArchiveKind::Uncompressed
+        );
+        assert_eq!(kind(b"PK\x03\x04", "tool"), ArchiveKind::Zip);
+        // unknown formats fall back to a name
+        assert_eq!(kind(b"??", "tool.tar.xz"), ArchiveKind::Tar(TarKind::XZ));
+    }
+
+    #[test]
+    fn executable_targets() {
+        // the test binary itself is built for this system
+        let this = executable(&std::env::current_exe().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(this.archs, vec![Arch::host()]);
+        assert_eq!(this.mismatch(), None);
+
+        // a 64-bit little endian ELF header of an aarch64 FreeBSD binary
+        let mut elf = vec![0x7f, b'E', b'L', b'F', 2, 1, 1, 9];
+        elf.resize(18, 0);
+        elf.extend_from_slice(&183u16.to_le_bytes());
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&elf).unwrap();
+
+        let other = executable(file.path()).unwrap().unwrap();
+        assert_eq!(other.archs, vec![Arch::Aarch64]);
+        assert_eq!(
+            other.mismatch().as_deref(),
+            Some("an ELF executable for aarch64 (FreeBSD)")
+        );
+    }
+
+    #[test]
+    fn compatible_archs() {
+        use ExecutableKind::*;
+
+        assert!(runs_on(Elf, Arch::Aarch64, Arch::Aarch64));
+        assert!(runs_on(MachO, Arch::X86_64, Arch::Aarch64));
+        assert!(!runs_on(MachO, Arch::Aarch64, Arch::X86_64));
+        assert!(runs_on(Pe, Arch::X86, Arch::X86_64));
+        assert!(!runs_on(Pe, Arch::X86_64, Arch::X86));
+        assert!(!runs_on(Elf, Arch::X86_64, Arch::Aarch64));
+        assert!(!runs_on(Elf, Arch::Other(243, 64), Arch::X86_64));
+    }
+}
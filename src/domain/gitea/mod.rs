@@ -0,0 +1,190 @@
+mod release;
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use reqwest::{header, Client};
+use tempfile::TempDir;
+use url::Url;
+
+use self::release::GiteaRelease;
+use super::error::GithubError;
+use super::forge::{self, Forge, ForgeOptions};
+use super::github::{get_asset_name_matcher, match_single_asset, Asset, Release};
+use super::package::{match_kind, PackageMatchKind};
+
+type Result<T, E = GithubError> = std::result::Result<T, E>;
+
+/// Default Gitea/Forgejo REST API root (Codeberg), used when no other root is configured.
+pub const GT_API_URL: &str = "https://codeberg.org/api/v1";
+// the default maximum page size of Gitea instances
+const GT_PER_PAGE: usize = 50;
+
+pub struct Gitea {
+    client: Client,
+    api_url: String,
+    opts: ForgeOptions,
+    headers: header::HeaderMap,
+    token: Option<header::HeaderValue>,
+}
+
+impl Gitea {
+    /// Create a Gitea/Forgejo API client. `api_url` overrides the API root, e.g. for
+    /// a self-hosted instance (`https://git.example.com/api/v1`).
+    pub fn create(token: Option<&String>, api_url: Option<&str>, opts: ForgeOptions) -> Self {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            header::ACCEPT,
+            header::HeaderValue::from_static("application/json"),
+        );
+        headers.insert(
+            header::USER_AGENT,
+            header::HeaderValue::from_static("reqwest"),
+        );
+
+        let token =
+            token.map(|token| header::HeaderValue::from_str(&format!("token {}", token)).unwrap());
+
+        let api_url = api_url
+            .unwrap_or(GT_API_URL)
+            .trim_end_matches('/')
+            .to_owned();
+
+        Self {
+            client: reqwest::Client::new(),
+            api_url,
+            opts,
+            headers,
+            token,
+        }
+    }
+
+    /// Headers for a request to `url`. The token is only ever sent to the API host.
+    fn headers_for(&self, url: &str) -> header::HeaderMap {
+        let mut headers = self.headers.clone();
+        if let Some(token) = &self.token {
+            let host = |url: &str| {
+                Url::parse(url)
+                    .ok()
+                    .and_then(|url| url.host_str().map(str::to_owned))
+            };
+            if host(&self.api_url) == host(url) {
+                headers.insert(header::AUTHORIZATION, token.clone());
+            }
+        }
+        headers
+    }
+
+    async fn find_release_exact(
+        &self,
+        req_url: &str,
+        repo: &str,
+        asset_glob: Option<&str>,
+        asset_re: Option<&str>,
+    ) -> Result<Release> {
+        let resp = forge::send(
+            self.client.get(req_url).headers(self.headers_for(req_url)),
+            "fetching release",
+            &self.opts,
+        )
+        .await?;
+
+        forge::check_release_status(&resp, req_url)?;
+
+        let release: GiteaRelease = resp.json().await.context("parsing release response body")?;
+
+        let asset_matcher = get_asset_name_matcher(repo, asset_glob, asset_re)?;
+        match_single_asset(release.into(), &asset_matcher)
+    }
+
+    async fn find_release(
+        &self,
+        req_url: &str,
+        requested: &str,
+        repo: &str,
+        asset_glob: Option<&str>,
+        asset_re: Option<&str>,
+    ) -> Result<Release> {
+        let asset_matcher = get_asset_name_matcher(repo, asset_glob, asset_re)?;
+
+        forge::find_semver_release(
+            format!("{}?limit={}", req_url, GT_PER_PAGE),
+            requested,
+            &asset_matcher,
+            &self.opts,
+            |page_url, _| async move {
+                let resp = forge::send(
+                    self.client
+                        .get(&page_url)
+                        .headers(self.headers_for(&page_url)),
+                    "sending request",
+                    &self.opts,
+                )
+                .await?;
+                forge::check_release_status(&resp, &page_url)?;
+
+                let next_page_url = forge::next_page_url(&resp);
+                let releases: Vec<GiteaRelease> =
+                    resp.json().await.context("parsing response body")?;
+                Ok(forge::ReleasesPage {
+                    releases: releases.into_iter().map(Release::from).collect(),
+                    next_page_url,
+                    validators: None,
+                })
+            },
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl Forge for Gitea {
+    async fn find_new(
+        &self,
+        user: &str,
+        repo: &str,
+        requested: &str,
+        asset_glob: Option<&str>,
+        asset_re: Option<&str>,
+    ) -> Result<Release> {
+        match match_kind(requested) {
+            PackageMatchKind::Latest => {
+                let req_url = format!("{}/repos/{}/{}/releases/latest", &self.api_url, user, repo);
+                self.find_release_exact(&req_url, repo, asset_glob, asset_re)
+                    .await
+            }
+            PackageMatchKind::Exact => {
+                let req_url = format!(
+                    "{}/repos/{}/{}/releases/tags/{}",
+                    &self.api_url, user, repo, requested,
+                );
+                self.find_release_exact(&req_url, repo, asset_glob, asset_re)
+                    .await
+            }
+            PackageMatchKind::SemVer => {
+                let req_url = format!("{}/repos/{}/{}/releases", &self.api_url, user, repo);
+                self.find_release(&req_url, requested, repo, asset_glob, asset_re)
+                    .await
+            }
+        }
+    }
+
+    async fn download(
+        &self,
+        _user: &str,
+        _repo: &str,
+        asset: &Asset,
+        temp_dir: &TempDir,
+    ) -> Result<PathBuf> {
+        let req_url = asset.browser_download_url.as_str();
+
+        forge::download(
+            self.client.get(req_url).headers(self.headers_for(req_url)),
+            asset,
+            temp_dir,
+            &self.opts,
+        )
+        .await
+    }
+}
@@ -0,0 +1,67 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use url::Url;
+
+use crate::domain::github::{Asset, Release};
+
+/// A Gitea/Forgejo release, as returned by the Gitea Releases API.
+#[derive(Debug, Deserialize)]
+pub struct GiteaRelease {
+    pub id: u64,
+    pub tag_name: String,
+    pub target_commitish: Option<String>,
+    pub draft: bool,
+    pub prerelease: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub published_at: DateTime<Utc>,
+    pub assets: Vec<GiteaAsset>,
+}
+
+/// A release *attachment*, Gitea's equivalent of a release asset.
+#[derive(Debug, Deserialize)]
+pub struct GiteaAsset {
+    pub id: u64,
+    pub name: String,
+    pub size: u64,
+    pub download_count: u64,
+    pub created_at: DateTime<Utc>,
+    pub browser_download_url: Url,
+}
+
+impl From<GiteaRelease> for Release {
+    fn from(release: GiteaRelease) -> Self {
+        let assets = release
+            .assets
+            .into_iter()
+            .map(|asset| Asset {
+                id: asset.id,
+                name: asset.name,
+                state: "uploaded".to_owned(),
+                content_type: "application/octet-stream".to_owned(),
+                size: asset.size,
+                download_count: asset.download_count,
+                created_at: asset.created_at,
+                // attachments can't be replaced in place
+                updated_at: asset.created_at,
+                browser_download_url: asset.browser_download_url,
+            })
+            .collect();
+
+        Release {
+            assets_url: None,
+            upload_url: None,
+            id: release.id,
+            node_id: None,
+            tag_name: release.tag_name,
+            target_commitish: release.target_commitish,
+            draft: release.draft,
+            prerelease: release.prerelease,
+            created_at: release.created_at,
+            published_at: release.published_at,
+            assets,
+            validators: None,
+            checksum_assets: vec![],
+            signature_assets: vec![],
+        }
+    }
+}
@@ -1,9 +1,11 @@
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
+use url::Url;
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[non_exhaustive]
+#[allow(dead_code)]
 pub struct Asset {
     pub id: u64,
     pub name: String,
@@ -13,4 +15,5 @@ pub struct Asset {
     pub download_count: u64,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub browser_download_url: Url,
 }
@@ -2,39 +2,47 @@ mod asset;
 mod release;
 mod response;
 
-use std::cmp;
 use std::fmt::Write;
 use std::path::PathBuf;
+use std::{iter, mem};
 
 use anyhow::{anyhow, Context};
-use futures_util::StreamExt;
-use indicatif::{ProgressBar, ProgressStyle};
+use async_trait::async_trait;
 use reqwest::{header, Client, Method};
 use tempfile::TempDir;
-use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
 
 use crate::domain::github::response::ErrorResponse;
 
-use self::release::Release;
+pub use self::asset::Asset;
+pub use self::release::Release;
 use self::response::GithubResponse;
+use super::cache::AssetCache;
+use super::checksum;
 use super::error::GithubError;
-use super::package::{match_kind, Package, PackageMatchKind};
+use super::forge::{self, Forge, ForgeOptions, Validators};
+use super::package::{match_kind, PackageMatchKind};
+use super::signature;
 use super::util;
 
 type Result<T, E = GithubError> = std::result::Result<T, E>;
+pub type AssetNameMatcher = Box<dyn Fn(&str) -> bool + Send + Sync>;
 
-const GH_MAX_PAGES: usize = 5;
-const GH_PER_PAGE: usize = 25;
+/// Default GitHub REST API root, used when no other root is configured.
+pub const GH_API_URL: &str = "https://api.github.com";
+const GH_PER_PAGE: usize = 100;
 
 pub struct GitHub {
     client: Client,
+    api_url: String,
+    opts: ForgeOptions,
     api_headers: header::HeaderMap,
     dl_headers: header::HeaderMap,
 }
 
 impl GitHub {
-    pub fn create(token: Option<&String>) -> Self {
+    /// Create a GitHub API client. `api_url` overrides the API root, e.g. for
+    /// GitHub Enterprise Server (`https://ghe.example.com/api/v3`) or a local mock server.
+    pub fn create(token: Option<&String>, api_url: Option<&str>, opts: ForgeOptions) -> Self {
         let mut api_headers = header::HeaderMap::new();
         api_headers.insert(
             header::ACCEPT,
@@ -68,103 +76,43 @@ impl GitHub {
             );
         }
 
+        let api_url = api_url
+            .unwrap_or(GH_API_URL)
+            .trim_end_matches('/')
+            .to_owned();
+
         Self {
             client: reqwest::Client::new(),
+            api_url,
+            opts,
             api_headers,
             dl_headers,
         }
     }
 
-    /// Find a `Release` matching provided parameters.
-    pub async fn find_new(
-        &self,
-        user: &str,
-        repo: &str,
-        requested: &str,
-        asset_glob: Option<&str>,
-        asset_re: Option<&str>,
-    ) -> Result<Release> {
-        match match_kind(requested) {
-            PackageMatchKind::Latest => {
-                let req_url = format!(
-                    "https://api.github.com/repos/{}/{}/releases/latest",
-                    user, repo,
-                );
-                self.find_release_exact(&req_url, repo, asset_glob, asset_re)
-                    .await
-            }
-            PackageMatchKind::Exact => {
-                let req_url = format!(
-                    "https://api.github.com/repos/{}/{}/releases/tags/{}",
-                    user, repo, requested,
-                );
-                self.find_release_exact(&req_url, repo, asset_glob, asset_re)
-                    .await
-            }
-            PackageMatchKind::SemVer => {
-                let req_url = format!(
-                    "https://api.github.com/repos/{}/{}/releases?per_page={}",
-                    user, repo, GH_PER_PAGE,
-                );
-                self.find_release(&req_url, requested, repo, asset_glob, asset_re)
-                    .await
-            }
-        }
-    }
-
-    /// Find a `Release` matching provided `Package`.
-    /// When `force` is `true`, return `Release`, even if it's not newer than
-    /// the one specified in `Package`
-    pub async fn find_existing(&self, package: &Package) -> Result<Release> {
-        let res = self
-            .find_new(
-                &package.user,
-                &package.repo,
-                &package.requested,
-                package.asset_glob.as_deref(),
-                package.asset_re.as_deref(),
-            )
-            .await;
-
-        if let Ok(release) = res {
-            // we want to compare release's `published_at` date to
-            // what we have on record. If it's the same as ours, skip it.
-            // NB: Strict comparison for equality should be faster and enough.
-            if release.tag_name == package.tag && release.published_at == package.timestamp {
-                Err(GithubError::AlreadyUpToDate)
-            } else {
-                Ok(release)
-            }
-        } else {
-            res
-        }
-    }
-
     async fn find_release_exact(
         &self,
         req_url: &str,
         repo: &str,
         asset_glob: Option<&str>,
         asset_re: Option<&str>,
+        validators: Option<&Validators>,
     ) -> Result<Release> {
         use reqwest::StatusCode;
 
-        let resp = self
-            .client
-            .get(req_url)
-            .headers(self.api_headers.clone())
-            .send()
-            .await
-            .context("fetching latest release")?;
-
-        if resp.status() == StatusCode::NOT_FOUND {
-            return Err(GithubError::ReleaseNotFound);
+        let mut req = self.client.get(req_url).headers(self.api_headers.clone());
+        if let Some(validators) = validators {
+            req = validators.apply(req);
         }
+        let resp = forge::send(req, "fetching latest release", &self.opts).await?;
 
-        if resp.status() != StatusCode::OK {
-            return Err(GithubError::AnyHow(anyhow!("getting")));
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            return Err(GithubError::AlreadyUpToDate(None));
         }
 
+        forge::check_release_status(&resp, req_url)?;
+
+        let validators = Validators::from_response(&resp);
         let resp: GithubResponse<Release> = resp
             .json()
             .await
@@ -172,26 +120,9 @@ impl GitHub {
 
         match resp {
             GithubResponse::Ok(mut release) => {
+                release.validators = validators;
                 let asset_matcher = get_asset_name_matcher(repo, asset_glob, asset_re)?;
-                release.assets.retain(|asset| asset_matcher(&asset.name));
-
-                match release.assets.len() {
-                    1 => Ok(release),
-                    0 => Err(GithubError::AssetNoMatch),
-                    _ => {
-                        let mut msg: String = String::new();
-                        for asset in &release.assets {
-                            writeln!(
-                                msg,
-                                "  {} ({})",
-                                &asset.name,
-                                bytesize::to_string(asset.size, false)
-                            )
-                            .map_err(anyhow::Error::msg)?;
-                        }
-                        Err(GithubError::AssetMultipleMatch(msg))
-                    }
-                }
+                match_single_asset(release, &asset_matcher)
             }
             GithubResponse::Err(ErrorResponse { message }) => {
                 Err(GithubError::AnyHow(anyhow!(message)))
@@ -206,147 +137,194 @@ impl GitHub {
         repo: &str,
         asset_glob: Option<&str>,
         asset_re: Option<&str>,
+        validators: Option<&Validators>,
     ) -> Result<Release> {
         use reqwest::StatusCode;
         let asset_matcher = get_asset_name_matcher(repo, asset_glob, asset_re)?;
-        let mut curr_page: usize = 1;
-
-        'outer: loop {
-            let resp = self
-                .client
-                .request(Method::GET, req_url)
-                .headers(self.api_headers.clone())
-                .query(&[("page", curr_page)])
-                .send()
-                .await
-                .context("sending request")?;
-
-            if resp.status() == StatusCode::NOT_FOUND {
-                return Err(GithubError::ReleaseNotFound);
-            }
 
-            if resp.status() != StatusCode::OK {
-                return Err(GithubError::AnyHow(anyhow!("getting")));
-            }
-
-            let releases: GithubResponse<Vec<Release>> =
-                resp.json().await.context("parsing response body")?;
+        forge::find_semver_release(
+            req_url.to_owned(),
+            requested,
+            &asset_matcher,
+            &self.opts,
+            |page_url, page| async move {
+                let mut req = self
+                    .client
+                    .request(Method::GET, &page_url)
+                    .headers(self.api_headers.clone());
+                if let (1, Some(validators)) = (page, validators) {
+                    req = validators.apply(req);
+                }
+                let resp = forge::send(req, "sending request", &self.opts).await?;
 
-            let releases = match releases {
-                GithubResponse::Ok(res) => res,
-                GithubResponse::Err(ErrorResponse { message }) => {
-                    return Err(GithubError::AnyHow(anyhow!(message)));
+                if resp.status() == StatusCode::NOT_MODIFIED {
+                    return Err(GithubError::AlreadyUpToDate(None));
                 }
-            };
-
-            for mut release in releases {
-                if util::matches_semver(&release.tag_name, requested) {
-                    release.assets.retain(|asset| asset_matcher(&asset.name));
-
-                    match release.assets.len() {
-                        1 => break 'outer Ok(release),
-                        0 => break 'outer Err(GithubError::ReleaseNotFound),
-                        _ => {
-                            let mut msg: String = String::new();
-                            for asset in &release.assets {
-                                writeln!(
-                                    msg,
-                                    "  {} ({})",
-                                    &asset.name,
-                                    bytesize::to_string(asset.size, false)
-                                )
-                                .map_err(anyhow::Error::msg)?;
-                            }
-                            break 'outer Err(GithubError::AssetMultipleMatch(msg));
-                        }
+                forge::check_release_status(&resp, &page_url)?;
+
+                let next_page_url = forge::next_page_url(&resp);
+                let validators = Validators::from_response(&resp);
+                let releases: GithubResponse<Vec<Release>> =
+                    resp.json().await.context("parsing response body")?;
+
+                match releases {
+                    GithubResponse::Ok(releases) => Ok(forge::ReleasesPage {
+                        releases,
+                        next_page_url,
+                        validators,
+                    }),
+                    GithubResponse::Err(ErrorResponse { message }) => {
+                        Err(GithubError::AnyHow(anyhow!(message)))
                     }
                 }
-            }
+            },
+        )
+        .await
+    }
+}
 
-            curr_page += 1;
-            if curr_page > GH_MAX_PAGES {
-                break Err(GithubError::ReleaseNotFound);
+#[async_trait]
+impl Forge for GitHub {
+    async fn find_new(
+        &self,
+        user: &str,
+        repo: &str,
+        requested: &str,
+        asset_glob: Option<&str>,
+        asset_re: Option<&str>,
+    ) -> Result<Release> {
+        self.find_changed(user, repo, requested, asset_glob, asset_re, None)
+            .await
+    }
+
+    async fn find_changed(
+        &self,
+        user: &str,
+        repo: &str,
+        requested: &str,
+        asset_glob: Option<&str>,
+        asset_re: Option<&str>,
+        validators: Option<&Validators>,
+    ) -> Result<Release> {
+        match match_kind(requested) {
+            PackageMatchKind::Latest => {
+                let req_url = format!("{}/repos/{}/{}/releases/latest", &self.api_url, user, repo);
+                self.find_release_exact(&req_url, repo, asset_glob, asset_re, validators)
+                    .await
+            }
+            PackageMatchKind::Exact => {
+                let req_url = format!(
+                    "{}/repos/{}/{}/releases/tags/{}",
+                    &self.api_url, user, repo, requested,
+                );
+                self.find_release_exact(&req_url, repo, asset_glob, asset_re, validators)
+                    .await
+            }
+            PackageMatchKind::SemVer => {
+                let req_url = format!(
+                    "{}/repos/{}/{}/releases?per_page={}",
+                    &self.api_url, user, repo, GH_PER_PAGE,
+                );
+                self.find_release(&req_url, requested, repo, asset_glob, asset_re, validators)
+                    .await
             }
         }
     }
 
-    pub async fn download(
+    async fn download(
         &self,
         user: &str,
         repo: &str,
-        asset_id: u64,
-        asset_name: &str,
+        asset: &Asset,
         temp_dir: &TempDir,
     ) -> Result<PathBuf> {
-        use reqwest::StatusCode;
+        // the cache is an optimization, so it's fine to carry on without one
+        let cache = AssetCache::open().ok();
+        let cache_key = AssetCache::key(&self.api_url, asset);
+
+        if let Some(path) = cache
+            .as_ref()
+            .and_then(|cache| cache.get(&cache_key, asset, temp_dir))
+        {
+            if self.opts.verbose {
+                self.opts
+                    .message(&format!("using a cached asset: {}", &asset.name));
+            }
+            return Ok(path);
+        }
+
         let req_url = format!(
-            "https://api.github.com/repos/{}/{}/releases/assets/{}",
-            user, repo, asset_id
+            "{}/repos/{}/{}/releases/assets/{}",
+            &self.api_url, user, repo, asset.id
         );
 
-        let resp = self
-            .client
-            .get(&req_url)
-            .headers(self.dl_headers.clone())
-            .send()
-            .await
-            .context("fething an asset")?;
-
-        if resp.status() == StatusCode::NOT_FOUND {
-            return Err(GithubError::AssetNotFound);
-        }
-
-        if resp.status() != StatusCode::OK {
-            let mut msg = format!("getting: {}", &req_url);
-            if let Ok(txt) = resp.text().await {
-                msg.push('\n');
-                msg.push_str(&txt);
+        let path = forge::download(
+            self.client.get(&req_url).headers(self.dl_headers.clone()),
+            asset,
+            temp_dir,
+            &self.opts,
+        )
+        .await?;
+
+        if let Some(cache) = cache {
+            // not worth failing a download for
+            if let Err(e) = cache.put(&cache_key, &path) {
+                self.opts
+                    .warn(&format!("{} not cached: {:#}", &asset.name, e));
             }
-            return Err(GithubError::AnyHow(anyhow!(msg)));
         }
-        let tot_size = resp.content_length().context("getting content length")?;
-
-        let pb = ProgressBar::new(tot_size);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("[{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
-                .unwrap()
-                .progress_chars("##-")
-        );
 
-        let mut downloaded: u64 = 0;
-        let mut stream = resp.bytes_stream();
+        Ok(path)
+    }
+}
 
-        let temp_file_name = temp_dir.path().join(asset_name);
-        let mut temp_file = File::create(temp_file_name.as_path())
-            .await
-            .context(format!(
-                "creating a temp file: {:?}",
-                temp_file_name.as_path(),
-            ))?;
-
-        while let Some(item) = stream.next().await {
-            let chunk = item.context("retrieving a next chunk")?;
-            temp_file
-                .write(&chunk)
-                .await
-                .context("writing a chunk to temp file")?;
-            let new = cmp::min(downloaded + (chunk.len() as u64), tot_size);
-            downloaded = new;
-            pb.set_position(new);
+/// Narrow down `release` assets to the one matched by `asset_matcher`.
+pub fn match_single_asset(
+    mut release: Release,
+    asset_matcher: &AssetNameMatcher,
+) -> Result<Release> {
+    let (matched, others): (Vec<_>, Vec<_>) = mem::take(&mut release.assets)
+        .into_iter()
+        .partition(|asset| asset_matcher(&asset.name));
+    release.assets = matched;
+
+    match release.assets.len() {
+        1 => {
+            let asset_name = release.assets[0].name.as_str();
+            release.checksum_assets = checksum::sibling_assets(&others, asset_name);
+            let signed_names: Vec<_> = iter::once(asset_name)
+                .chain(
+                    release
+                        .checksum_assets
+                        .iter()
+                        .map(|asset| asset.name.as_str()),
+                )
+                .collect();
+            release.signature_assets = signature::sibling_assets(&others, &signed_names);
+            Ok(release)
+        }
+        0 => Err(GithubError::AssetNoMatch),
+        _ => {
+            let mut msg: String = String::new();
+            for asset in &release.assets {
+                writeln!(
+                    msg,
+                    "  {} ({})",
+                    &asset.name,
+                    bytesize::to_string(asset.size, false)
+                )
+                .map_err(anyhow::Error::msg)?;
+            }
+            Err(GithubError::AssetMultipleMatch(msg))
         }
-
-        pb.finish_and_clear();
-
-        Ok(temp_file_name)
     }
 }
 
-fn get_asset_name_matcher(
+pub fn get_asset_name_matcher(
     repo: &str,
     asset_glob: Option<&str>,
     asset_re: Option<&str>,
-) -> Result<Box<dyn Fn(&str) -> bool>> {
+) -> Result<AssetNameMatcher> {
     if let Some(s) = asset_glob {
         if s.contains('/') || s.contains("**") {
             return Err(GithubError::AnyHow(anyhow!("'/' or '**' are not allowed not allowed in a glob pattern matching a single file name")));
@@ -3,20 +3,31 @@ use serde::Deserialize;
 use url::Url;
 
 use super::asset::Asset;
+use crate::domain::forge::Validators;
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[non_exhaustive]
+#[allow(dead_code)]
 pub struct Release {
-    pub assets_url: Url,
-    pub upload_url: Url,
+    pub assets_url: Option<Url>,
+    pub upload_url: Option<Url>,
     pub id: u64,
-    pub node_id: String,
+    pub node_id: Option<String>,
     pub tag_name: String,
-    pub target_commitish: String,
+    pub target_commitish: Option<String>,
     pub draft: bool,
     pub prerelease: bool,
     pub created_at: Option<DateTime<Utc>>,
     pub published_at: DateTime<Utc>,
     pub assets: Vec<Asset>,
+    /// validators of a response this release came from, for conditional requests
+    #[serde(skip)]
+    pub validators: Option<Validators>,
+    /// checksum assets published alongside a matched asset (see `match_single_asset`)
+    #[serde(skip)]
+    pub checksum_assets: Vec<Asset>,
+    /// signature assets of a matched asset, and of its checksum assets (see `match_single_asset`)
+    #[serde(skip)]
+    pub signature_assets: Vec<Asset>,
 }
@@ -0,0 +1,202 @@
+mod release;
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use reqwest::{header, Client};
+use tempfile::TempDir;
+use url::Url;
+
+use self::release::GitlabRelease;
+use super::error::GithubError;
+use super::forge::{self, Forge, ForgeOptions};
+use super::github::{get_asset_name_matcher, match_single_asset, Asset, Release};
+use super::package::{match_kind, PackageMatchKind};
+
+type Result<T, E = GithubError> = std::result::Result<T, E>;
+
+/// Default GitLab REST API root, used when no other root is configured.
+pub const GL_API_URL: &str = "https://gitlab.com/api/v4";
+const GL_PER_PAGE: usize = 100;
+
+pub struct GitLab {
+    client: Client,
+    api_url: String,
+    opts: ForgeOptions,
+    headers: header::HeaderMap,
+    token: Option<header::HeaderValue>,
+}
+
+impl GitLab {
+    /// Create a GitLab API client. `api_url` overrides the API root, e.g. for
+    /// a self-managed instance (`https://gitlab.example.com/api/v4`).
+    pub fn create(token: Option<&String>, api_url: Option<&str>, opts: ForgeOptions) -> Self {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            header::USER_AGENT,
+            header::HeaderValue::from_static("reqwest"),
+        );
+
+        let token = token.map(|token| header::HeaderValue::from_str(token).unwrap());
+
+        let api_url = api_url
+            .unwrap_or(GL_API_URL)
+            .trim_end_matches('/')
+            .to_owned();
+
+        Self {
+            client: reqwest::Client::new(),
+            api_url,
+            opts,
+            headers,
+            token,
+        }
+    }
+
+    /// Build a `/projects/:id/releases[/...]` endpoint URL, where `:id` is
+    /// the URL-encoded `user/repo` path (`user` may include subgroups).
+    fn releases_url(&self, user: &str, repo: &str, segments: &[&str]) -> Result<Url> {
+        let mut url = Url::parse(&self.api_url).context("parsing GitLab API URL")?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("GitLab API URL can't be a base: {}", &self.api_url))?
+            .push("projects")
+            .push(&format!("{}/{}", user, repo))
+            .push("releases")
+            .extend(segments);
+        Ok(url)
+    }
+
+    /// Headers for a request to `url`. The token is only ever sent to the API host,
+    /// since asset links may point anywhere.
+    fn headers_for(&self, url: &Url) -> header::HeaderMap {
+        let mut headers = self.headers.clone();
+        if let Some(token) = &self.token {
+            let api_host = Url::parse(&self.api_url)
+                .ok()
+                .and_then(|api_url| api_url.host_str().map(str::to_owned));
+            if api_host.as_deref() == url.host_str() {
+                headers.insert("PRIVATE-TOKEN", token.clone());
+            }
+        }
+        headers
+    }
+
+    async fn find_release_exact(
+        &self,
+        req_url: Url,
+        repo: &str,
+        asset_glob: Option<&str>,
+        asset_re: Option<&str>,
+    ) -> Result<Release> {
+        let resp = forge::send(
+            self.client
+                .get(req_url.clone())
+                .headers(self.headers_for(&req_url)),
+            "fetching release",
+            &self.opts,
+        )
+        .await?;
+
+        forge::check_release_status(&resp, req_url.as_str())?;
+
+        let release: GitlabRelease = resp.json().await.context("parsing release response body")?;
+
+        let asset_matcher = get_asset_name_matcher(repo, asset_glob, asset_re)?;
+        match_single_asset(release.into(), &asset_matcher)
+    }
+
+    async fn find_release(
+        &self,
+        req_url: Url,
+        requested: &str,
+        repo: &str,
+        asset_glob: Option<&str>,
+        asset_re: Option<&str>,
+    ) -> Result<Release> {
+        let asset_matcher = get_asset_name_matcher(repo, asset_glob, asset_re)?;
+        let mut first_page_url = req_url;
+        first_page_url
+            .query_pairs_mut()
+            .append_pair("per_page", &GL_PER_PAGE.to_string());
+
+        forge::find_semver_release(
+            first_page_url.into(),
+            requested,
+            &asset_matcher,
+            &self.opts,
+            |page_url, _| async move {
+                let page_url = Url::parse(&page_url).context("parsing a page URL")?;
+                let resp = forge::send(
+                    self.client
+                        .get(page_url.clone())
+                        .headers(self.headers_for(&page_url)),
+                    "sending request",
+                    &self.opts,
+                )
+                .await?;
+                forge::check_release_status(&resp, page_url.as_str())?;
+
+                let next_page_url = forge::next_page_url(&resp);
+                let releases: Vec<GitlabRelease> =
+                    resp.json().await.context("parsing response body")?;
+                Ok(forge::ReleasesPage {
+                    releases: releases.into_iter().map(Release::from).collect(),
+                    next_page_url,
+                    validators: None,
+                })
+            },
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl Forge for GitLab {
+    async fn find_new(
+        &self,
+        user: &str,
+        repo: &str,
+        requested: &str,
+        asset_glob: Option<&str>,
+        asset_re: Option<&str>,
+    ) -> Result<Release> {
+        match match_kind(requested) {
+            PackageMatchKind::Latest => {
+                let req_url = self.releases_url(user, repo, &["permalink", "latest"])?;
+                self.find_release_exact(req_url, repo, asset_glob, asset_re)
+                    .await
+            }
+            PackageMatchKind::Exact => {
+                let req_url = self.releases_url(user, repo, &[requested])?;
+                self.find_release_exact(req_url, repo, asset_glob, asset_re)
+                    .await
+            }
+            PackageMatchKind::SemVer => {
+                let req_url = self.releases_url(user, repo, &[])?;
+                self.find_release(req_url, requested, repo, asset_glob, asset_re)
+                    .await
+            }
+        }
+    }
+
+    async fn download(
+        &self,
+        _user: &str,
+        _repo: &str,
+        asset: &Asset,
+        temp_dir: &TempDir,
+    ) -> Result<PathBuf> {
+        let req_url = &asset.browser_download_url;
+
+        forge::download(
+            self.client
+                .get(req_url.clone())
+                .headers(self.headers_for(req_url)),
+            asset,
+            temp_dir,
+            &self.opts,
+        )
+        .await
+    }
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use url::Url;
+
+use crate::domain::github::{Asset, Release};
+
+/// A GitLab release, as returned by the GitLab Releases API.
+#[derive(Debug, Deserialize)]
+pub struct GitlabRelease {
+    pub tag_name: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub released_at: DateTime<Utc>,
+    #[serde(default)]
+    pub upcoming_release: bool,
+    pub assets: GitlabAssets,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitlabAssets {
+    #[serde(default)]
+    pub links: Vec<GitlabLink>,
+}
+
+/// A release *asset link*, GitLab's equivalent of a release asset.
+#[derive(Debug, Deserialize)]
+pub struct GitlabLink {
+    pub id: u64,
+    pub name: String,
+    pub url: Url,
+    pub direct_asset_url: Option<Url>,
+    pub link_type: Option<String>,
+}
+
+impl From<GitlabRelease> for Release {
+    fn from(release: GitlabRelease) -> Self {
+        let released_at = release.released_at;
+        let assets = release
+            .assets
+            .links
+            .into_iter()
+            .map(|link| Asset {
+                id: link.id,
+                name: link.name,
+                state: "uploaded".to_owned(),
+                content_type: link.link_type.unwrap_or_else(|| "other".to_owned()),
+                // GitLab doesn't report sizes nor download counts of asset links
+                size: 0,
+                download_count: 0,
+                created_at: released_at,
+                updated_at: released_at,
+                browser_download_url: link.direct_asset_url.unwrap_or(link.url),
+            })
+            .collect();
+
+        Release {
+            assets_url: None,
+            upload_url: None,
+            // GitLab identifies releases by their tag only
+            id: 0,
+            node_id: None,
+            tag_name: release.tag_name,
+            target_commitish: None,
+            draft: false,
+            prerelease: release.upcoming_release,
+            created_at: release.created_at,
+            published_at: released_at,
+            assets,
+            validators: None,
+            checksum_assets: vec![],
+            signature_assets: vec![],
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use std::ffi::OsStr;
 use std::fmt::Write;
 use std::fs::{self, File, OpenOptions};
-use std::io::BufReader;
-use std::path::Path;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
 #[cfg(target_family = "unix")]
 use std::{
     fs::{set_permissions, Permissions},
@@ -11,34 +11,55 @@ use std::{
 
 use anyhow::{anyhow, Context};
 use bzip2::read::BzDecoder;
+use directories::BaseDirs;
 use flate2::read::GzDecoder;
 use lazy_static::__Deref;
+use lz4_flex::frame::FrameDecoder;
+use sevenz_rust::{Password, SevenZReader};
+use tempfile::{NamedTempFile, TempPath};
 use xz::read::XzDecoder;
 use zip::ZipArchive;
+use zstd::stream::read::Decoder as ZstdDecoder;
 
 use super::error::InstallerError;
+use super::format;
+use super::package::ExtraBin;
+use super::side_files;
+use super::store::Layout;
 use super::util::{self, ArchiveKind, TarKind};
 
 type Result<T, E = InstallerError> = std::result::Result<T, E>;
 
+/// File name prefix of binaries being installed, and of replaced ones on Windows.
+const STAGED_PREFIX: &str = ".gitrel-";
+type ArchiveEntryMatcher = Box<dyn Fn(&Path) -> Result<bool>>;
+
 #[cfg(not(target_os = "windows"))]
-pub async fn install(
+#[allow(clippy::too_many_arguments)]
+pub fn install(
     asset_name: &str,
     asset_path: &Path,
     bin_dir: &Path,
     bin_name: &str,
+    extra_bins: &[ExtraBin],
+    store_dir: Option<&Path>,
     strip: bool,
+    ignore_arch: bool,
     entry_glob: Option<&str>,
     entry_re: Option<&str>,
 ) -> Result<u64> {
-    let dest = bin_dir.join(&bin_name);
-    let dest = dest.as_path();
+    let install_dir = store_dir.unwrap_or(bin_dir);
+    let staged = stage(install_dir)?;
+    let dest: &Path = &staged;
 
-    let bin_size = match util::archive_kind(asset_name) {
+    let bin_size = match format::archive_kind(asset_path, asset_name)? {
         ArchiveKind::GZip => extract_gzip(asset_path, dest),
         ArchiveKind::BZip => extract_bzip(asset_path, dest),
         ArchiveKind::XZ => extract_xz(asset_path, dest),
+        ArchiveKind::Zstd => extract_zstd(asset_path, dest),
+        ArchiveKind::Lz4 => extract_lz4(asset_path, dest),
         ArchiveKind::Zip => extract_zip(asset_path, bin_name, dest, entry_glob, entry_re),
+        ArchiveKind::SevenZip => extract_7z(asset_path, bin_name, dest, entry_glob, entry_re),
         ArchiveKind::Tar(tar_kind) => {
             extract_tar(asset_path, tar_kind, bin_name, dest, entry_glob, entry_re)
         }
@@ -48,6 +69,7 @@ pub async fn install(
             let mut dest_file = OpenOptions::new()
                 .write(true)
                 .create(true)
+                .truncate(true)
                 .open(dest)
                 .context(format!(
                     "{}:{}: {}",
@@ -64,41 +86,73 @@ pub async fn install(
             }
         }
     }?;
+    check_executable(dest, bin_name, ignore_arch)?;
 
-    cfg_if::cfg_if! {
-        if #[cfg(target_family = "unix")] {
-            set_permissions(dest, Permissions::from_mode(0o755)).context("setting the execution bit")?;
-            if strip {
-                std::process::Command::new("strip").arg(dest).output().context("stripping the executable")?;
-                let bin_size = fs::metadata(dest).context("getting installed binary metadata")?.len();
-                Ok(bin_size)
-            } else {
-                Ok(bin_size)
-            }
-        } else {
-            Ok(bin_size)
+    // before the binary is in place, so that it's never seen not executable, or unstripped
+    let bin_size = prepare(dest, bin_size, strip)?;
+
+    // all binaries are extracted, before any of them is replaced
+    let mut staged_bins = vec![(staged, bin_name.to_owned())];
+    for extra_bin in extra_bins {
+        let staged = stage(install_dir)?;
+        let bin_size = extract_entry(asset_name, asset_path, &extra_bin.entry, &staged)?;
+        check_executable(&staged, extra_bin.bin_name(), ignore_arch)?;
+        prepare(&staged, bin_size, strip)?;
+        staged_bins.push((staged, extra_bin.bin_name().to_owned()));
+    }
+
+    for (staged, bin_name) in staged_bins {
+        replace(staged, &install_dir.join(&bin_name))?;
+        if let Some(store_dir) = store_dir {
+            link(&store_dir.join(&bin_name), &bin_dir.join(&bin_name))?;
         }
     }
+    Ok(bin_size)
+}
+
+/// Make a `staged` binary executable, and strip it if asked to. Returns its final size.
+#[cfg(target_family = "unix")]
+fn prepare(staged: &Path, bin_size: u64, strip: bool) -> Result<u64> {
+    set_permissions(staged, Permissions::from_mode(0o755)).context("setting the execution bit")?;
+    if !strip {
+        return Ok(bin_size);
+    }
+
+    std::process::Command::new("strip")
+        .arg(staged)
+        .output()
+        .context("stripping the executable")?;
+    Ok(fs::metadata(staged)
+        .context("getting installed binary metadata")?
+        .len())
 }
 
 #[cfg(target_os = "windows")]
-pub async fn install(
+#[allow(clippy::too_many_arguments)]
+pub fn install(
     asset_name: &str,
     asset_path: &Path,
     bin_dir: &Path,
     bin_name: &str,
+    extra_bins: &[ExtraBin],
+    store_dir: Option<&Path>,
+    ignore_arch: bool,
     entry_glob: Option<&str>,
     entry_re: Option<&str>,
 ) -> Result<u64> {
     let bin_name = format!("{}.exe", bin_name);
-    let dest = bin_dir.join(&bin_name);
-    let dest = dest.as_path();
+    let install_dir = store_dir.unwrap_or(bin_dir);
+    let staged = stage(install_dir)?;
+    let dest: &Path = &staged;
 
-    match util::archive_kind(asset_name) {
+    let bin_size = match format::archive_kind(asset_path, asset_name)? {
         ArchiveKind::GZip => extract_gzip(asset_path, dest),
         ArchiveKind::BZip => extract_bzip(asset_path, dest),
         ArchiveKind::XZ => extract_xz(asset_path, dest),
+        ArchiveKind::Zstd => extract_zstd(asset_path, dest),
+        ArchiveKind::Lz4 => extract_lz4(asset_path, dest),
         ArchiveKind::Zip => extract_zip(asset_path, &bin_name, dest, entry_glob, entry_re),
+        ArchiveKind::SevenZip => extract_7z(asset_path, &bin_name, dest, entry_glob, entry_re),
         ArchiveKind::Tar(tar_kind) => {
             extract_tar(asset_path, tar_kind, &bin_name, dest, entry_glob, entry_re)
         }
@@ -108,6 +162,7 @@ pub async fn install(
             let mut dest_file = OpenOptions::new()
                 .write(true)
                 .create(true)
+                .truncate(true)
                 .open(dest)
                 .context(format!(
                     "{}:{}: {}",
@@ -123,7 +178,267 @@ pub async fn install(
                 ))),
             }
         }
+    }?;
+    check_executable(dest, &bin_name, ignore_arch)?;
+
+    // all binaries are extracted, before any of them is replaced
+    let mut staged_bins = vec![(staged, bin_name)];
+    for extra_bin in extra_bins {
+        let staged = stage(install_dir)?;
+        let entry = with_exe(&extra_bin.entry);
+        extract_entry(asset_name, asset_path, &entry, &staged)?;
+        check_executable(&staged, &entry, ignore_arch)?;
+        staged_bins.push((staged, with_exe(extra_bin.bin_name())));
+    }
+
+    for (staged, bin_name) in staged_bins {
+        replace(staged, &install_dir.join(&bin_name))?;
+        if let Some(store_dir) = store_dir {
+            link(&store_dir.join(&bin_name), &bin_dir.join(&bin_name))?;
+        }
+    }
+    Ok(bin_size)
+}
+
+#[cfg(target_os = "windows")]
+fn with_exe(name: &str) -> String {
+    if name.to_lowercase().ends_with(".exe") {
+        name.to_owned()
+    } else {
+        format!("{}.exe", name)
+    }
+}
+
+/// Make sure a binary `bin_name` extracted into `staged` is an executable, rather than
+/// i.e. a readme, and one that can be run on the current platform, unless `ignore_arch`
+/// (which also lets executables of formats not told apart be installed).
+fn check_executable(staged: &Path, bin_name: &str, ignore_arch: bool) -> Result<()> {
+    if ignore_arch {
+        return Ok(());
+    }
+
+    let executable = format::executable(staged)?.ok_or_else(|| {
+        InstallerError::NotExecutable(bin_name.to_owned(), "of an unknown format".to_owned())
+    })?;
+    match executable.mismatch() {
+        Some(mismatch) => Err(InstallerError::TargetMismatch(
+            bin_name.to_owned(),
+            mismatch,
+            format!("{} {}", std::env::consts::ARCH, std::env::consts::OS),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Extract an archive entry with a file name `entry` into `dest`.
+fn extract_entry(asset_name: &str, asset_path: &Path, entry: &str, dest: &Path) -> Result<u64> {
+    match format::archive_kind(asset_path, asset_name)? {
+        ArchiveKind::Zip => extract_zip(asset_path, entry, dest, None, None),
+        ArchiveKind::SevenZip => extract_7z(asset_path, entry, dest, None, None),
+        ArchiveKind::Tar(tar_kind) => extract_tar(asset_path, tar_kind, entry, dest, None, None),
+        _ => Err(InstallerError::AnyHow(anyhow!(
+            "more binaries can only be installed from zip, 7z or tar archives, not {}",
+            asset_name
+        ))),
+    }
+}
+
+/// Install a kept binary at `stored_path` (see `store`) into `bin_dir` as `bin_name`,
+/// replacing an installed one, either by linking to it, or by copying it.
+pub fn restore(stored_path: &Path, bin_dir: &Path, bin_name: &str, layout: Layout) -> Result<u64> {
+    if layout == Layout::Link {
+        link(stored_path, &util::bin_path(bin_dir, bin_name))?;
+        let bin_size = fs::metadata(stored_path)
+            .context("getting installed binary metadata")?
+            .len();
+        return Ok(bin_size);
+    }
+
+    let staged = stage(bin_dir)?;
+    // permissions are copied as well
+    let bin_size = fs::copy(stored_path, &staged)
+        .with_context(|| format!("copying a kept binary: {:?}", stored_path))?;
+
+    replace(staged, &util::bin_path(bin_dir, bin_name))?;
+    Ok(bin_size)
+}
+
+/// Install conventional side files of binaries named `bin_names` (shell completions and
+/// man pages, see `side_files`), found in an archive asset, into a home dir, replacing
+/// installed ones. Returns their paths.
+pub fn install_side_files(
+    asset_name: &str,
+    asset_path: &Path,
+    bin_names: &[&str],
+) -> Result<Vec<PathBuf>> {
+    let base_dirs = BaseDirs::new().ok_or_else(|| anyhow!("unable to get usable `base dir`"))?;
+    let home_dir = base_dirs.home_dir();
+    let mut installed = Vec::new();
+
+    let mut install = |entry: &Path, reader: &mut dyn Read| -> Result<()> {
+        let dest = match side_files::dest(entry, bin_names) {
+            Some(dest) => home_dir.join(dest),
+            None => return Ok(()),
+        };
+        // i.e. the same completion for several shells, the first one wins
+        if installed.contains(&dest) {
+            return Ok(());
+        }
+
+        let dest_dir = dest.parent().context("side file dir")?;
+        fs::create_dir_all(dest_dir).with_context(|| format!("creating {:?}", dest_dir))?;
+        let staged = stage(dest_dir)?;
+        let mut dest_file = File::create(&staged).context("opening destination")?;
+        std::io::copy(reader, &mut dest_file)
+            .with_context(|| format!("extracting {}", entry.display()))?;
+        drop(dest_file);
+
+        replace(staged, &dest)?;
+        installed.push(dest);
+        Ok(())
+    };
+
+    match format::archive_kind(asset_path, asset_name)? {
+        ArchiveKind::Zip => {
+            let mut zip = ZipArchive::new(File::open(asset_path).context("opening a zip file")?)
+                .context("reading a zip file")?;
+            for i in 0..zip.len() {
+                let mut file_entry = zip.by_index(i).context("indexing into a zip file")?;
+                let archive_entry = match file_entry.enclosed_name() {
+                    Some(archive_entry) if file_entry.is_file() => archive_entry.to_owned(),
+                    _ => continue,
+                };
+                install(&archive_entry, &mut file_entry)?;
+            }
+        }
+        ArchiveKind::SevenZip => {
+            let mut failure = None;
+            SevenZReader::open(asset_path, Password::empty())
+                .and_then(|mut sevenz| {
+                    sevenz.for_each_entries(|file_entry, reader| {
+                        if !file_entry.is_directory() {
+                            if let Err(e) = install(Path::new(file_entry.name()), reader) {
+                                failure = Some(e);
+                                return Ok(false);
+                            }
+                        }
+                        // the next entry is read where this one ends
+                        io::copy(reader, &mut io::sink())?;
+                        Ok(true)
+                    })
+                })
+                .context("reading a 7z file")?;
+            if let Some(e) = failure {
+                return Err(e);
+            }
+        }
+        ArchiveKind::Tar(tar_kind) => {
+            let tarball = decompress_tar(asset_path, tar_kind)?;
+            let tarball_path = tarball.as_deref().unwrap_or(asset_path);
+            let reader = BufReader::new(File::open(tarball_path).context("reading a tarball")?);
+            let mut tarball = tar::Archive::new(reader);
+            for entry in tarball.entries().context("reading tarball entries")? {
+                let mut entry = entry.context("reading a tarball entry")?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let archive_entry = entry
+                    .path()
+                    .context("getting a tarball entry path")?
+                    .into_owned();
+                install(&archive_entry, &mut entry)?;
+            }
+        }
+        // a single binary
+        _ => (),
     }
+
+    Ok(installed)
+}
+
+/// A temp file in `bin_dir` to extract a binary into, before it replaces an installed one.
+/// Being in the same dir (hence on the same file system), it can be renamed over it.
+fn stage(bin_dir: &Path) -> Result<TempPath> {
+    Ok(tempfile::Builder::new()
+        .prefix(STAGED_PREFIX)
+        .tempfile_in(bin_dir)
+        .with_context(|| format!("creating a temp file in {:?}", bin_dir))?
+        // not kept open, for extractors to (re)create it as they see fit
+        .into_temp_path())
+}
+
+/// Atomically replace a binary at `dest` (if any) with a `staged` one, so that a binary
+/// is never seen partially written, even after a crash, and a running one keeps running.
+fn replace(staged: TempPath, dest: &Path) -> Result<()> {
+    // extractors write through their own handles, and `strip` replaces the file,
+    // so sync whatever is at the staged path now
+    OpenOptions::new()
+        .write(true)
+        .open(&staged)
+        .and_then(|file| file.sync_all())
+        .context("syncing an installed binary")?;
+
+    rename_into(staged, dest)
+}
+
+/// Link `dest` to a binary at `target` with a symlink, or with a hardlink where symlinks
+/// aren't supported (i.e. on Windows, without a developer mode), replacing an installed one.
+fn link(target: &Path, dest: &Path) -> Result<()> {
+    let bin_dir = dest.parent().context("install dir of a binary")?;
+    let staged = stage(bin_dir)?;
+    fs::remove_file(&staged).context("making room for a link")?;
+
+    #[cfg(target_family = "unix")]
+    let res = std::os::unix::fs::symlink(target, &staged);
+    #[cfg(target_os = "windows")]
+    let res = std::os::windows::fs::symlink_file(target, &staged);
+    res.or_else(|_| fs::hard_link(target, &staged))
+        .with_context(|| format!("linking {:?} to {:?}", dest, target))?;
+
+    rename_into(staged, dest)
+}
+
+/// Rename a `staged` file over `dest`, making the rename durable.
+fn rename_into(staged: TempPath, dest: &Path) -> Result<()> {
+    // a running binary can't be replaced on Windows, but can be renamed out of the way
+    #[cfg(target_os = "windows")]
+    let replaced = {
+        let file_name = dest.file_name().unwrap_or_default().to_string_lossy();
+        let replaced = dest.with_file_name(format!("{}{}.old", STAGED_PREFIX, file_name));
+        // i.e. left by a previous update, while it was running
+        let _ = fs::remove_file(&replaced);
+        if dest.exists() {
+            fs::rename(dest, &replaced).context("moving a replaced binary aside")?;
+            Some(replaced)
+        } else {
+            None
+        }
+    };
+
+    let res = staged.persist(dest).map_err(|e| e.error);
+
+    #[cfg(target_os = "windows")]
+    if let Some(replaced) = replaced {
+        if res.is_err() {
+            // put the replaced binary back, rather than leave none in place
+            let _ = fs::rename(&replaced, dest);
+        } else {
+            // fails while it's still running, to be removed by a next update
+            let _ = fs::remove_file(replaced);
+        }
+    }
+
+    res.with_context(|| format!("replacing {:?}", dest))?;
+
+    // make the rename itself durable
+    #[cfg(target_family = "unix")]
+    if let Some(bin_dir) = dest.parent() {
+        File::open(bin_dir)
+            .and_then(|dir| dir.sync_all())
+            .context("syncing an install dir")?;
+    }
+
+    Ok(())
 }
 
 // TODO: maybe use flate2's tokio stuff?
@@ -134,6 +449,7 @@ fn extract_gzip(archive: &Path, dest: &Path) -> Result<u64> {
     let mut dest_file = OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .open(dest)
         .context(format!(
             "{}:{}: {}",
@@ -154,6 +470,7 @@ fn extract_bzip(archive: &Path, dest: &Path) -> Result<u64> {
     let mut dest_file = OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .open(dest)
         .context(format!(
             "{}:{}: {}",
@@ -176,6 +493,7 @@ fn extract_xz(archive: &Path, dest: &Path) -> Result<u64> {
     let mut dest_file = OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .open(dest)
         .context(format!(
             "{}:{}: {}",
@@ -189,6 +507,49 @@ fn extract_xz(archive: &Path, dest: &Path) -> Result<u64> {
     }
 }
 
+fn extract_zstd(archive: &Path, dest: &Path) -> Result<u64> {
+    let mut reader = BufReader::new(
+        ZstdDecoder::new(File::open(archive).context("opening a zstd file")?)
+            .context("reading a zstd file")?,
+    );
+    let mut dest_file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(dest)
+        .context(format!(
+            "{}:{}: {}",
+            file!(),
+            line!(),
+            "opening destination"
+        ))?;
+    match std::io::copy(&mut reader, &mut dest_file) {
+        Ok(n) => Ok(n),
+        Err(_e) => Err(InstallerError::AnyHow(anyhow!("decompressing a zstd file"))),
+    }
+}
+
+fn extract_lz4(archive: &Path, dest: &Path) -> Result<u64> {
+    let mut reader = BufReader::new(FrameDecoder::new(
+        File::open(archive).context("opening an lz4 file")?,
+    ));
+    let mut dest_file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(dest)
+        .context(format!(
+            "{}:{}: {}",
+            file!(),
+            line!(),
+            "opening destination"
+        ))?;
+    match std::io::copy(&mut reader, &mut dest_file) {
+        Ok(n) => Ok(n),
+        Err(_e) => Err(InstallerError::AnyHow(anyhow!("decompressing an lz4 file"))),
+    }
+}
+
 fn extract_zip(
     archive: &Path,
     file_name: &str,
@@ -223,6 +584,7 @@ fn extract_zip(
         let mut dest_file = OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(true)
             .open(dest)
             .context(format!(
                 "{}:{}: {}",
@@ -246,6 +608,67 @@ fn extract_zip(
     ))
 }
 
+fn extract_7z(
+    archive: &Path,
+    file_name: &str,
+    dest: &Path,
+    entry_glob: Option<&str>,
+    entry_re: Option<&str>,
+) -> Result<u64> {
+    let archive_entry_matcher = get_archive_entry_matcher(file_name, entry_glob, entry_re)?;
+    let mut unmatched_entries = String::new();
+    // entries are only read in order, so an outcome is kept aside, when matched
+    let mut extracted = None;
+
+    SevenZReader::open(archive, Password::empty())
+        .and_then(|mut sevenz| {
+            sevenz.for_each_entries(|file_entry, reader| {
+                if file_entry.is_directory() {
+                    return Ok(true);
+                }
+
+                let archive_entry = Path::new(file_entry.name());
+                match archive_entry_matcher(archive_entry) {
+                    Ok(true) => {
+                        extracted = Some(
+                            OpenOptions::new()
+                                .write(true)
+                                .create(true)
+                                .truncate(true)
+                                .open(dest)
+                                .and_then(|mut dest_file| std::io::copy(reader, &mut dest_file))
+                                .map_err(|_e| {
+                                    InstallerError::AnyHow(anyhow!("decompressing a 7z file"))
+                                }),
+                        );
+                        Ok(false)
+                    }
+                    Ok(false) => {
+                        let _ = writeln!(&mut unmatched_entries, "  {}", archive_entry.display());
+                        // the next entry is read where this one ends
+                        io::copy(reader, &mut io::sink())?;
+                        Ok(true)
+                    }
+                    Err(e) => {
+                        extracted = Some(Err(e));
+                        Ok(false)
+                    }
+                }
+            })
+        })
+        .context("reading a 7z file")?;
+
+    extracted.unwrap_or_else(|| {
+        Err(entry_match_error(
+            archive.file_name().and_then(OsStr::to_str).unwrap(),
+            file_name,
+            entry_glob,
+            entry_re,
+            unmatched_entries,
+        ))
+    })
+}
+
 fn extract_tar(
     archive: &Path,
     tar_kind: TarKind,
@@ -254,26 +677,9 @@ fn extract_tar(
     entry_glob: Option<&str>,
     entry_re: Option<&str>,
 ) -> Result<u64> {
-    let tarball_path = match tar_kind {
-        TarKind::GZip => {
-            let uncompressed = archive.with_extension("");
-            extract_gzip(archive, &uncompressed)?;
-            uncompressed
-        }
-        TarKind::BZip => {
-            let uncompressed = archive.with_extension("");
-            extract_bzip(archive, &uncompressed)?;
-            uncompressed
-        }
-        TarKind::XZ => {
-            let uncompressed = archive.with_extension("");
-            extract_xz(archive, &uncompressed)?;
-            uncompressed
-        }
-        TarKind::Uncompressed => archive.to_path_buf(),
-    };
-
-    let reader = BufReader::new(File::open(&tarball_path).context("reading a tarball")?);
+    let tarball = decompress_tar(archive, tar_kind)?;
+    let tarball_path = tarball.as_deref().unwrap_or(archive);
+    let reader = BufReader::new(File::open(tarball_path).context("reading a tarball")?);
     let mut tarball = tar::Archive::new(reader);
     let archive_entry_matcher = get_archive_entry_matcher(file_name, entry_glob, entry_re)?;
     let mut unmatched_entries = String::new();
@@ -303,6 +709,27 @@ fn extract_tar(
     ))
 }
 
+/// An uncompressed tarball of a (compressed) tarball `archive`, decompressed into a temp
+/// file next to it, or `None` if `archive` isn't compressed.
+fn decompress_tar(archive: &Path, tar_kind: TarKind) -> Result<Option<TempPath>> {
+    let decompress = match tar_kind {
+        TarKind::GZip => extract_gzip,
+        TarKind::BZip => extract_bzip,
+        TarKind::XZ => extract_xz,
+        TarKind::Zstd => extract_zstd,
+        TarKind::Lz4 => extract_lz4,
+        TarKind::Uncompressed => return Ok(None),
+    };
+
+    // not `archive` with its extension dropped, which is `archive` itself for an
+    // extensionless asset, truncated while being read
+    let uncompressed = NamedTempFile::new_in(archive.parent().unwrap_or_else(|| Path::new(".")))
+        .context("creating a temp file")?
+        .into_temp_path();
+    decompress(archive, &uncompressed)?;
+    Ok(Some(uncompressed))
+}
+
 fn entry_match_error(
     archive_name: &str,
     entry_exact: &str,
@@ -328,7 +755,7 @@ fn get_archive_entry_matcher(
     entry_exact: &str,
     entry_glob: Option<&str>,
     entry_re: Option<&str>,
-) -> Result<Box<dyn Fn(&Path) -> Result<bool>>> {
+) -> Result<ArchiveEntryMatcher> {
     if let Some(s) = entry_glob {
         let glob = glob::Pattern::new(s).context("invalid asset name glob pattern")?;
         Ok(Box::new(move |archive_entry: &Path| {
@@ -346,3 +773,145 @@ fn get_archive_entry_matcher(
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use flate2::{write::GzEncoder, Compression};
+    use lz4_flex::frame::FrameEncoder;
+    use sevenz_rust::{SevenZArchiveEntry, SevenZWriter};
+
+    use super::*;
+
+    const BIN_NAMES: [&str; 2] = ["tool", "tool-helper"];
+
+    fn script(name: &str) -> String {
+        format!("#!/bin/sh\necho {}\n", name)
+    }
+
+    /// An uncompressed tarball of `BIN_NAMES` scripts.
+    fn tarball() -> Vec<u8> {
+        let mut tarball = tar::Builder::new(Vec::new());
+        for name in BIN_NAMES {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(script(name).len() as u64);
+            header.set_mode(0o755);
+            tarball
+                .append_data(
+                    &mut header,
+                    format!("dist/{}", name),
+                    script(name).as_bytes(),
+                )
+                .unwrap();
+        }
+        tarball.into_inner().unwrap()
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn lz4(data: &[u8]) -> Vec<u8> {
+        let mut encoder = FrameEncoder::new(Vec::new());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// Install `bins` from an asset `asset_name` at `asset_path`, with `contents`,
+    /// into a temp dir, checking they come out intact.
+    fn install_asset(asset_name: &str, asset_path: &Path, contents: &[u8], bins: &[&str]) {
+        File::create(asset_path)
+            .and_then(|mut file| file.write_all(contents))
+            .unwrap();
+        let bin_dir = tempfile::tempdir().unwrap();
+        let extra_bins: Vec<ExtraBin> = bins[1..].iter().map(|bin| bin.parse().unwrap()).collect();
+
+        let bin_size = install(
+            asset_name,
+            asset_path,
+            bin_dir.path(),
+            bins[0],
+            &extra_bins,
+            None,
+            false,
+            false,
+            None,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(bin_size, script(bins[0]).len() as u64, "{}", asset_name);
+        for name in bins {
+            assert_eq!(
+                fs::read_to_string(bin_dir.path().join(name)).unwrap(),
+                script(name),
+                "{}",
+                asset_name
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn install_extensionless_tarball() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let asset_path = temp_dir.path().join("tool-linux-amd64");
+        let asset = gzip(&tarball());
+
+        install_asset("tool-linux-amd64", &asset_path, &asset, &BIN_NAMES);
+
+        // neither truncated, nor left next to it decompressed
+        assert_eq!(fs::read(&asset_path).unwrap(), asset);
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn install_zstd_lz4_and_7z() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let asset_path = |name: &str| temp_dir.path().join(name);
+
+        let bin = script("tool");
+        let compressed = [
+            (
+                "tool-linux.zst",
+                zstd::encode_all(bin.as_bytes(), 0).unwrap(),
+            ),
+            ("tool-linux.lz4", lz4(bin.as_bytes())),
+        ];
+        for (name, contents) in compressed {
+            install_asset(name, &asset_path(name), &contents, &BIN_NAMES[..1]);
+        }
+
+        let archives = [
+            (
+                "tool-linux.tar.zst",
+                zstd::encode_all(&*tarball(), 0).unwrap(),
+            ),
+            ("tool-linux.tar.lz4", lz4(&tarball())),
+        ];
+        for (name, contents) in archives {
+            install_asset(name, &asset_path(name), &contents, &BIN_NAMES);
+        }
+
+        let mut seven_zip = SevenZWriter::new(Cursor::new(Vec::new())).unwrap();
+        for name in BIN_NAMES {
+            let mut entry = SevenZArchiveEntry::new();
+            entry.name = format!("dist/{}", name);
+            entry.has_stream = true;
+            seven_zip
+                .push_archive_entry(entry, Some(script(name).as_bytes()))
+                .unwrap();
+        }
+        let seven_zip = seven_zip.finish().unwrap().into_inner();
+        install_asset(
+            "tool_linux.7z",
+            &asset_path("tool_linux.7z"),
+            &seven_zip,
+            &BIN_NAMES,
+        );
+    }
+}
@@ -1,7 +1,18 @@
+pub mod cache;
+pub mod checksum;
 pub mod error;
 pub mod executor;
+pub mod forge;
+pub mod format;
+pub mod gitea;
 pub mod github;
+pub mod gitlab;
 pub mod installer;
 pub mod package;
+pub mod plain_url;
+pub mod side_files;
+pub mod signature;
+pub mod sigstore;
+pub mod store;
 pub mod uninstaller;
 pub mod util;
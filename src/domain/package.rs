@@ -1,19 +1,47 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::checksum::ChecksumPolicy;
+use super::forge::{ForgeKind, Validators};
+use super::plain_url::UrlSource;
+use super::sigstore::SigstoreIdentity;
+use super::store::{Layout, StoredVersion};
+
 /// Representation an installed package.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Package {
+    /// forge hosting the repo (GitHub when not specified)
+    #[serde(default)]
+    pub forge: ForgeKind,
     /// user name
     pub user: String,
     /// repo name
     pub repo: String,
+    /// forge API root URL, when not using the default one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+    /// download URL template and version discovery of a `ForgeKind::Url` package
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<UrlSource>,
     /// binary name to use
     pub bin_name: String,
+    /// more binaries installed from an archive asset, along with `bin_name`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bins: Vec<ExtraBin>,
+    /// don't install shell completions and man pages shipped with binaries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_side_files: Option<bool>,
+    /// install binaries built for another OS or architecture
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore_arch: Option<bool>,
+    /// shell completions and man pages installed along with binaries
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub side_files: Vec<PathBuf>,
     /// override installation path
     #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,
@@ -30,6 +58,33 @@ pub struct Package {
     pub strip: Option<bool>,
     /// When remote repo was last updated
     pub timestamp: DateTime<Utc>,
+    /// SHA-256 digest of the installed binary, as of `tag`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// size of the installed binary, as of `tag`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// how the binary is installed
+    #[serde(default)]
+    pub layout: Layout,
+    /// versions kept in the store to roll back to, the most recently installed last
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub versions: Vec<StoredVersion>,
+    /// number of versions to keep in the store, `store::DEFAULT_KEEP` if not recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep: Option<u32>,
+    /// validators of the release endpoint response, as of `tag`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validators: Option<Validators>,
+    /// what to do about checksums published alongside an asset
+    #[serde(default)]
+    pub checksum: ChecksumPolicy,
+    /// trusted public key (minisign or SSH) release assets are signed with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// signer identity sigstore bundles of release assets are issued to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sigstore: Option<SigstoreIdentity>,
     /// asset name contais
     #[serde(skip_serializing_if = "Option::is_none")]
     pub asset_glob: Option<String>,
@@ -48,6 +103,50 @@ pub struct Package {
     pub cmd_after: Option<String>,
 }
 
+/// A binary installed from an archive entry, in addition to a package's main one.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ExtraBin {
+    /// archive entry file name
+    pub entry: String,
+    /// binary name to use, when not the entry's one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// SHA-256 digest of the installed binary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// size of the installed binary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+impl ExtraBin {
+    pub fn bin_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.entry)
+    }
+}
+
+impl FromStr for ExtraBin {
+    type Err = String;
+
+    /// Parse an `ENTRY[:NAME]` command line spec.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let (entry, name) = match spec.split_once(':') {
+            Some((entry, name)) => (entry, Some(name.to_owned())),
+            None => (spec, None),
+        };
+        if entry.is_empty() || name.as_deref() == Some("") {
+            return Err(format!("expected ENTRY[:NAME], got `{}`", spec));
+        }
+
+        Ok(Self {
+            entry: entry.to_owned(),
+            name,
+            sha256: None,
+            size: None,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub enum PackageMatchKind {
     Exact,
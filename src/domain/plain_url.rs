@@ -0,0 +1,281 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::{header, Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use tempfile::TempDir;
+use url::Url;
+
+use super::error::GithubError;
+use super::forge::{self, Forge, ForgeOptions};
+use super::github::{Asset, Release};
+use super::package::{match_kind, PackageMatchKind};
+use super::util;
+
+type Result<T, E = GithubError> = std::result::Result<T, E>;
+
+/// A package published at a templated URL, rather than on a forge.
+///
+/// Template placeholders:
+/// - `{version}` - a version, with a leading `v` trimmed
+/// - `{tag}` - a version, as discovered
+/// - `{os}`, `{arch}` - Rust's names for the host OS and architecture (i.e. `macos`, `x86_64`)
+/// - `{goos}`, `{goarch}` - Go's names for the same (i.e. `darwin`, `amd64`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UrlSource {
+    /// download URL template
+    pub template: String,
+    /// how to discover available versions
+    pub versions: VersionSource,
+}
+
+/// A method of discovering available versions of a `UrlSource`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionSource {
+    /// a fixed list of versions
+    List(Vec<String>),
+    /// a JSON endpoint, and a JSON pointer to a version string or an array of them
+    Json { url: String, pointer: String },
+}
+
+pub struct PlainUrl {
+    client: Client,
+    headers: header::HeaderMap,
+    source: UrlSource,
+    opts: ForgeOptions,
+}
+
+impl PlainUrl {
+    pub fn create(source: UrlSource, opts: ForgeOptions) -> Self {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            header::USER_AGENT,
+            header::HeaderValue::from_static("reqwest"),
+        );
+
+        Self {
+            client: reqwest::Client::new(),
+            headers,
+            source,
+            opts,
+        }
+    }
+
+    async fn versions(&self) -> Result<Vec<String>> {
+        match &self.source.versions {
+            VersionSource::List(versions) => Ok(versions.clone()),
+            VersionSource::Json { url, pointer } => {
+                let resp = forge::send(
+                    self.client.get(url).headers(self.headers.clone()),
+                    "fetching versions",
+                    &self.opts,
+                )
+                .await?;
+
+                if resp.status() != StatusCode::OK {
+                    return Err(GithubError::AnyHow(anyhow!("getting: {}", url)));
+                }
+
+                let json: serde_json::Value = resp
+                    .json()
+                    .await
+                    .context("parsing versions response body")?;
+
+                versions_at(&json, pointer).ok_or_else(|| {
+                    GithubError::AnyHow(anyhow!(
+                        "JSON pointer `{}` doesn't point to a version string or an array of them in {}",
+                        pointer,
+                        url
+                    ))
+                })
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Forge for PlainUrl {
+    async fn find_new(
+        &self,
+        _user: &str,
+        _repo: &str,
+        requested: &str,
+        _asset_glob: Option<&str>,
+        _asset_re: Option<&str>,
+    ) -> Result<Release> {
+        let versions = self.versions().await?;
+
+        let version = match match_kind(requested) {
+            PackageMatchKind::Latest => latest(versions.iter()).or_else(|| versions.first()),
+            PackageMatchKind::SemVer => latest(
+                versions
+                    .iter()
+                    .filter(|version| util::matches_semver(version, requested)),
+            ),
+            PackageMatchKind::Exact => versions.iter().find(|version| *version == requested),
+        }
+        .ok_or(GithubError::ReleaseNotFound)?;
+
+        let url = render(&self.source.template, version);
+        let url = Url::parse(&url).context(format!("invalid package URL: {}", url))?;
+        let name = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| anyhow!("package URL has no file name: {}", url))?
+            .to_owned();
+
+        // there is no publish date to go by, so the version alone identifies a release
+        let published_at = DateTime::<Utc>::default();
+
+        Ok(Release {
+            assets_url: None,
+            upload_url: None,
+            id: 0,
+            node_id: None,
+            tag_name: version.to_owned(),
+            target_commitish: None,
+            draft: false,
+            prerelease: false,
+            created_at: None,
+            published_at,
+            assets: vec![Asset {
+                id: 0,
+                name,
+                state: "uploaded".to_owned(),
+                content_type: "application/octet-stream".to_owned(),
+                size: 0,
+                download_count: 0,
+                created_at: published_at,
+                updated_at: published_at,
+                browser_download_url: url,
+            }],
+            validators: None,
+            checksum_assets: vec![],
+            signature_assets: vec![],
+        })
+    }
+
+    async fn download(
+        &self,
+        _user: &str,
+        _repo: &str,
+        asset: &Asset,
+        temp_dir: &TempDir,
+    ) -> Result<PathBuf> {
+        forge::download(
+            self.client
+                .get(asset.browser_download_url.clone())
+                .headers(self.headers.clone()),
+            asset,
+            temp_dir,
+            &self.opts,
+        )
+        .await
+    }
+
+    // a URL template is of a single asset, with no checksums alongside it
+    fn publishes_checksums(&self) -> bool {
+        false
+    }
+}
+
+/// Versions at a JSON `pointer`, to a version string or an array of them.
+fn versions_at(json: &serde_json::Value, pointer: &str) -> Option<Vec<String>> {
+    match json.pointer(pointer)? {
+        serde_json::Value::String(version) => Some(vec![version.to_owned()]),
+        serde_json::Value::Array(versions) => Some(
+            versions
+                .iter()
+                .filter_map(|version| version.as_str().map(str::to_owned))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// The highest *semver* among `versions`.
+fn latest<'a>(versions: impl Iterator<Item = &'a String>) -> Option<&'a String> {
+    versions
+        .filter_map(|version| {
+            let semver = util::SEMVER.find(version)?;
+            let semver = semver::Version::parse(semver.as_str()).ok()?;
+            Some((semver, version))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, version)| version)
+}
+
+/// Substitute placeholders in a URL `template`.
+fn render(template: &str, version: &str) -> String {
+    let (goos, goarch) = go_target();
+    template
+        .replace("{version}", version.trim_start_matches('v'))
+        .replace("{tag}", version)
+        .replace("{os}", std::env::consts::OS)
+        .replace("{arch}", std::env::consts::ARCH)
+        .replace("{goos}", goos)
+        .replace("{goarch}", goarch)
+}
+
+fn go_target() -> (&'static str, &'static str) {
+    let goos = match std::env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    };
+    let goarch = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "x86" => "386",
+        "aarch64" => "arm64",
+        arch => arch,
+    };
+    (goos, goarch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_placeholders() {
+        let (goos, goarch) = go_target();
+        assert_eq!(
+            render(
+                "https://example.com/tool/{version}/tool-{tag}-{os}-{arch}.tar.gz",
+                "v1.2.3"
+            ),
+            format!(
+                "https://example.com/tool/1.2.3/tool-v1.2.3-{}-{}.tar.gz",
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            )
+        );
+        assert_eq!(
+            render("tool_{version}_{goos}_{goarch}.zip", "0.9.0"),
+            format!("tool_0.9.0_{}_{}.zip", goos, goarch)
+        );
+    }
+
+    #[test]
+    fn discover_versions() {
+        let json: serde_json::Value = serde_json::from_str(
+            r#"{"stable": "v1.4.0", "releases": [{"v": 1}, "1.10.0", "v1.9.2", "nightly"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            versions_at(&json, "/stable"),
+            Some(vec!["v1.4.0".to_owned()])
+        );
+        let versions = versions_at(&json, "/releases").unwrap();
+        assert_eq!(versions, ["1.10.0", "v1.9.2", "nightly"]);
+        assert_eq!(versions_at(&json, "/releases/0"), None);
+        assert_eq!(versions_at(&json, "/missing"), None);
+
+        // the highest semver, not the first, nor the last one
+        assert_eq!(latest(versions.iter()).map(String::as_str), Some("1.10.0"));
+        assert_eq!(latest(["nightly".to_owned()].iter()), None);
+    }
+}
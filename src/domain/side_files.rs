@@ -0,0 +1,104 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Where zsh completions are installed, relative to a home dir.
+const ZSH_DIR: &str = ".local/share/zsh/site-functions";
+/// Where bash completions are installed, relative to a home dir (see `bash-completion`).
+const BASH_DIR: &str = ".local/share/bash-completion/completions";
+/// Where fish completions are installed, relative to a home dir.
+const FISH_DIR: &str = ".config/fish/completions";
+/// Where man pages are installed, relative to a home dir.
+const MAN_DIR: &str = ".local/share/man";
+
+/// Where a conventional side file of one of binaries named `bin_names` (a shell completion,
+/// or a man page), at `entry` of a release archive, is installed, relative to a home dir.
+/// Returns `None` for other entries.
+pub fn dest(entry: &Path, bin_names: &[&str]) -> Option<PathBuf> {
+    let file_name = entry.file_name()?.to_str()?;
+    // i.e. `completions/bash/tool`
+    let parent_name = entry
+        .parent()
+        .and_then(Path::file_name)
+        .and_then(|name| name.to_str());
+
+    bin_names.iter().find_map(|&bin_name| {
+        let dest =
+            if file_name == format!("_{}", bin_name) || file_name == format!("{}.zsh", bin_name) {
+                Path::new(ZSH_DIR).join(format!("_{}", bin_name))
+            } else if [".bash", ".bash-completion", "-completion.bash"]
+                .iter()
+                .any(|ext| file_name == format!("{}{}", bin_name, ext))
+                || (file_name == bin_name && parent_name == Some("bash"))
+            {
+                Path::new(BASH_DIR).join(bin_name)
+            } else if file_name == format!("{}.fish", bin_name) {
+                Path::new(FISH_DIR).join(file_name)
+            } else {
+                // i.e. `tool.1`, `tool-subcommand.1`, or `tool.1.gz`
+                let page = file_name.strip_suffix(".gz").unwrap_or(file_name);
+                let (name, section) = page.rsplit_once('.')?;
+                let is_section = matches!(section.as_bytes(), [b'1'..=b'9']);
+                let is_page = name == bin_name
+                    || name
+                        .strip_prefix(bin_name)
+                        .is_some_and(|rest| rest.starts_with('-'));
+                if !(is_section && is_page) {
+                    return None;
+                }
+                Path::new(MAN_DIR)
+                    .join(format!("man{}", section))
+                    .join(file_name)
+            };
+        Some(dest)
+    })
+}
+
+/// Remove installed side files at `paths`, other than `kept` ones.
+pub fn remove(paths: &[PathBuf], kept: &[PathBuf]) -> Result<()> {
+    for path in paths.iter().filter(|path| !kept.contains(path)) {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                return Err(e).with_context(|| format!("deleting a side file: {}", path.display()))
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn side_file_dests() {
+        let dest = |entry: &str| dest(Path::new(entry), &["rg", "tool"]);
+
+        assert_eq!(
+            dest("ripgrep-13.0.0/complete/_rg"),
+            Some(Path::new(ZSH_DIR).join("_rg"))
+        );
+        assert_eq!(dest("rg.bash"), Some(Path::new(BASH_DIR).join("rg")));
+        assert_eq!(
+            dest("completions/bash/tool"),
+            Some(Path::new(BASH_DIR).join("tool"))
+        );
+        assert_eq!(
+            dest("complete/rg.fish"),
+            Some(Path::new(FISH_DIR).join("rg.fish"))
+        );
+        assert_eq!(dest("doc/rg.1"), Some(Path::new(MAN_DIR).join("man1/rg.1")));
+        assert_eq!(
+            dest("man/tool-sub.5.gz"),
+            Some(Path::new(MAN_DIR).join("man5/tool-sub.5.gz"))
+        );
+
+        assert_eq!(dest("rg"), None);
+        assert_eq!(dest("doc/rg.md"), None);
+        assert_eq!(dest("doc/rga.1"), None);
+        assert_eq!(dest("tool.10"), None);
+    }
+}
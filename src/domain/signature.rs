@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use tempfile::TempDir;
+
+use super::error::InstallerError;
+use super::forge::Forge;
+use super::github::{Asset, Release};
+use super::sigstore;
+use super::util;
+
+type Result<T, E = InstallerError> = std::result::Result<T, E>;
+
+/// Namespace of signatures made with `ssh-keygen -Y sign -n file`.
+const SSH_NAMESPACE: &str = "file";
+
+/// A trusted public key of a package, that its release assets are signed with.
+pub enum PublicKey {
+    /// verifies `<asset>.minisig` signatures
+    Minisign(minisign_verify::PublicKey),
+    /// verifies `<asset>.sig` SSH signatures
+    Ssh(ssh_key::PublicKey),
+}
+
+impl PublicKey {
+    /// Parse a minisign public key (with or without an untrusted comment line),
+    /// or an OpenSSH public key (i.e. a line of `~/.ssh/id_ed25519.pub`).
+    pub fn parse(key: &str) -> anyhow::Result<Self> {
+        let key = key.trim();
+        if key.starts_with("ssh-") || key.starts_with("sk-") {
+            ssh_key::PublicKey::from_openssh(key)
+                .map(Self::Ssh)
+                .map_err(|e| anyhow!("invalid SSH public key: {}", e))
+        } else {
+            // the key itself is the last line of a `minisign.pub` file
+            let key = key.lines().last().unwrap_or_default().trim();
+            minisign_verify::PublicKey::from_base64(key)
+                .map(Self::Minisign)
+                .map_err(|e| anyhow!("invalid minisign public key: {}", e))
+        }
+    }
+
+    /// Read a public key given either as is, or as a path to a file containing it.
+    /// Returns the key as it is to be stored in a package.
+    pub fn read(key_or_path: &str) -> anyhow::Result<String> {
+        let key = if Path::new(key_or_path).is_file() {
+            fs::read_to_string(key_or_path)
+                .with_context(|| format!("reading public key: {}", key_or_path))?
+        } else {
+            key_or_path.to_owned()
+        };
+
+        let key = key.trim();
+        Ok(match Self::parse(key)? {
+            Self::Minisign(_) => key.lines().last().unwrap_or_default().trim().to_owned(),
+            Self::Ssh(_) => key.to_owned(),
+        })
+    }
+
+    fn signature_ext(&self) -> &'static str {
+        match self {
+            Self::Minisign(_) => "minisig",
+            Self::Ssh(_) => "sig",
+        }
+    }
+}
+
+/// Signature assets among `assets`, of assets named `signed_names`
+/// (i.e. of an asset, and of its checksum files).
+pub fn sibling_assets(assets: &[Asset], signed_names: &[&str]) -> Vec<Asset> {
+    let is_signature =
+        |ext: &str| ext == ".minisig" || ext == ".sig" || sigstore::BUNDLE_EXTS.contains(&ext);
+
+    assets
+        .iter()
+        .filter(|asset| {
+            signed_names.iter().any(|signed_name| {
+                asset
+                    .name
+                    .strip_prefix(signed_name)
+                    .is_some_and(is_signature)
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+/// Verify a downloaded `release` asset at `asset_path` against its signature,
+/// made by an owner of `public_key`. Only a signature is downloaded, the verification
+/// itself is done offline.
+pub async fn verify(
+    forge: &dyn Forge,
+    user: &str,
+    repo: &str,
+    release: &Release,
+    asset_path: &Path,
+    temp_dir: &TempDir,
+    public_key: &str,
+) -> Result<()> {
+    let public_key = PublicKey::parse(public_key).context("package public key")?;
+    let asset_name = &release.assets[0].name;
+    let signature_name = format!("{}.{}", asset_name, public_key.signature_ext());
+
+    let signature_asset = release
+        .signature_assets
+        .iter()
+        .find(|asset| asset.name == signature_name)
+        .ok_or_else(|| {
+            InstallerError::Signature(format!(
+                "{} not found, but the package has a public key",
+                signature_name
+            ))
+        })?;
+    let signature_path = forge
+        .download(user, repo, signature_asset, temp_dir)
+        .await
+        .map_err(anyhow::Error::from)?;
+    let signature = fs::read_to_string(&signature_path)
+        .with_context(|| format!("reading signature: {}", signature_name))?;
+    let asset_path = asset_path.to_path_buf();
+
+    let res = util::blocking(move || {
+        let asset = fs::read(asset_path).context("reading downloaded file")?;
+        Ok::<_, anyhow::Error>(match &public_key {
+            PublicKey::Minisign(public_key) => minisign_verify::Signature::decode(&signature)
+                // allow signatures of minisign versions before 0.8
+                .and_then(|signature| public_key.verify(&asset, &signature, true))
+                .map_err(|e| e.to_string()),
+            PublicKey::Ssh(public_key) => ssh_key::SshSig::from_pem(&signature)
+                .and_then(|signature| public_key.verify(SSH_NAMESPACE, &asset, &signature))
+                .map_err(|e| e.to_string()),
+        })
+    })
+    .await?;
+
+    res.map_err(|e| {
+        InstallerError::Signature(format!("{} of {}: {}", signature_name, asset_name, e))
+    })
+}
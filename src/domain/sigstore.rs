@@ -0,0 +1,180 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sigstore_verify::trust_root::{TrustedRoot, SIGSTORE_PRODUCTION_TRUSTED_ROOT};
+use sigstore_verify::types::Bundle;
+use sigstore_verify::{VerificationPolicy, Verifier};
+use tempfile::TempDir;
+
+use super::checksum;
+use super::error::InstallerError;
+use super::forge::Forge;
+use super::github::{Asset, Release};
+use super::util;
+
+type Result<T, E = InstallerError> = std::result::Result<T, E>;
+
+/// Name suffixes of sigstore bundles, as published by cosign (`--new-bundle-format`)
+/// and goreleaser.
+pub const BUNDLE_EXTS: [&str; 2] = [".sigstore.json", ".sigstore"];
+
+/// A downloaded bundle asset and its path, along with the name and path of the checksums
+/// file it signs, if any.
+type DownloadedBundle = (Asset, PathBuf, Option<(String, PathBuf)>);
+
+/// A signer, whose keyless (Fulcio) certificate sigstore bundles of package assets
+/// must be issued to.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SigstoreIdentity {
+    /// certificate identity glob pattern, i.e. an email, or a CI workflow URI
+    pub identity: String,
+    /// OIDC issuer of the identity, i.e. `https://token.actions.githubusercontent.com`
+    pub issuer: String,
+}
+
+/// Verify a downloaded `release` asset at `asset_path` against a sigstore bundle of
+/// either the asset itself, or of a checksums file listing it, as signed by `signer`.
+/// Only bundles (and checksums) are downloaded, the verification itself is done offline
+/// against the Sigstore public good trust root bundled with `gitrel`.
+pub async fn verify(
+    forge: &dyn Forge,
+    user: &str,
+    repo: &str,
+    release: &Release,
+    asset_path: &Path,
+    temp_dir: &TempDir,
+    signer: &SigstoreIdentity,
+) -> Result<()> {
+    let asset_name = release.assets[0].name.clone();
+
+    let mut bundles = Vec::new();
+    if let Some(bundle_asset) = find_bundle(release, &asset_name) {
+        let bundle_path = forge
+            .download(user, repo, bundle_asset, temp_dir)
+            .await
+            .map_err(anyhow::Error::from)?;
+        bundles.push((bundle_asset.clone(), bundle_path, None));
+    } else {
+        // i.e. goreleaser signs just a `checksums.txt`
+        for checksum_asset in &release.checksum_assets {
+            let bundle_asset = match find_bundle(release, &checksum_asset.name) {
+                Some(bundle_asset) => bundle_asset,
+                None => continue,
+            };
+
+            let bundle_path = forge
+                .download(user, repo, bundle_asset, temp_dir)
+                .await
+                .map_err(anyhow::Error::from)?;
+            let checksums_path = forge
+                .download(user, repo, checksum_asset, temp_dir)
+                .await
+                .map_err(anyhow::Error::from)?;
+            bundles.push((
+                bundle_asset.clone(),
+                bundle_path,
+                Some((checksum_asset.name.clone(), checksums_path)),
+            ));
+        }
+    }
+
+    let signer = signer.clone();
+    let asset_path = asset_path.to_path_buf();
+    util::blocking(move || verify_downloaded(&signer, &asset_name, &asset_path, &bundles)).await
+}
+
+/// Offline part of `verify`, for bundles already downloaded.
+fn verify_downloaded(
+    signer: &SigstoreIdentity,
+    asset_name: &str,
+    asset_path: &Path,
+    bundles: &[DownloadedBundle],
+) -> Result<()> {
+    let trusted_root = TrustedRoot::from_json(SIGSTORE_PRODUCTION_TRUSTED_ROOT)
+        .context("parsing the sigstore trust root")?;
+    let verifier = Verifier::new(&trusted_root).context("parsing the sigstore trust root")?;
+    // identities are matched here, since the library matches them exactly
+    let policy = VerificationPolicy::any_identity().require_issuer(signer.issuer.as_str());
+    let identity = glob::Pattern::new(&signer.identity).context("invalid sigstore identity")?;
+
+    for (bundle_asset, bundle_path, checksums) in bundles {
+        let (checksums_name, checksums_path) = match checksums {
+            Some(checksums) => checksums,
+            None => {
+                return verify_bundle(
+                    &verifier,
+                    &policy,
+                    &identity,
+                    bundle_path,
+                    bundle_asset,
+                    asset_path,
+                )
+            }
+        };
+
+        verify_bundle(
+            &verifier,
+            &policy,
+            &identity,
+            bundle_path,
+            bundle_asset,
+            checksums_path,
+        )?;
+
+        let checksums = fs::read_to_string(checksums_path)
+            .with_context(|| format!("reading checksums: {}", checksums_name))?;
+        if checksum::verify_listed(&checksums, checksums_name, asset_name, asset_path)
+            .map_err(anyhow::Error::from)?
+            .is_some()
+        {
+            return Ok(());
+        }
+    }
+
+    Err(InstallerError::Signature(format!(
+        "no sigstore bundle of {} or of its checksums found, but the package has a sigstore identity",
+        asset_name
+    )))
+}
+
+fn find_bundle<'a>(release: &'a Release, signed_name: &str) -> Option<&'a Asset> {
+    BUNDLE_EXTS.iter().find_map(|ext| {
+        let bundle_name = format!("{}{}", signed_name, ext);
+        release
+            .signature_assets
+            .iter()
+            .find(|asset| asset.name == bundle_name)
+    })
+}
+
+fn verify_bundle(
+    verifier: &Verifier,
+    policy: &VerificationPolicy,
+    identity: &glob::Pattern,
+    bundle_path: &Path,
+    bundle_asset: &Asset,
+    signed_path: &Path,
+) -> Result<()> {
+    let bundle = fs::read_to_string(bundle_path)
+        .with_context(|| format!("reading sigstore bundle: {}", &bundle_asset.name))?;
+    let signed = File::open(signed_path).context("opening downloaded file")?;
+
+    Bundle::from_json(&bundle)
+        .map_err(|e| e.to_string())
+        .and_then(|bundle| {
+            verifier
+                .verify_reader(signed, &bundle, policy)
+                .map_err(|e| e.to_string())
+        })
+        .and_then(|res| match res.identity() {
+            Some(actual) if identity.matches(actual.as_str()) => Ok(()),
+            Some(actual) => Err(format!(
+                "identity mismatch: expected {}, got {}",
+                identity, actual
+            )),
+            None => Err("no certificate identity".to_owned()),
+        })
+        .map_err(|e| InstallerError::Signature(format!("{}: {}", &bundle_asset.name, e)))
+}
@@ -0,0 +1,235 @@
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::package::{ExtraBin, Package};
+use super::util;
+
+/// Data sub-directory of kept package versions.
+pub const PKGS_DIR: &str = "pkgs";
+/// Number of installed versions of a package kept, unless specified.
+pub const DEFAULT_KEEP: u32 = 3;
+
+/// How binaries are installed into an install dir.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// a binary is copied into an install dir, and into the store to roll back to
+    #[default]
+    Copy,
+    /// a binary is installed into the store, and linked to from an install dir
+    Link,
+}
+
+/// An installed version of a package, kept in the store under
+/// `~/.local/share/gitrel/pkgs/<bin>/<tag>/`, to roll back to.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StoredVersion {
+    /// *release tag* of a kept version
+    pub tag: String,
+    /// when a release of a kept version was published
+    pub timestamp: DateTime<Utc>,
+    /// SHA-256 digest of a kept binary
+    pub sha256: String,
+    /// size of a kept binary
+    pub size: u64,
+    /// more binaries of a kept version, installed from the same asset
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bins: Vec<ExtraBin>,
+    /// shell completions and man pages installed along with a kept version
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub side_files: Vec<PathBuf>,
+}
+
+/// Keep binaries just installed into `bin_dir` as `pkg` in the store (unless they're
+/// linked to it already), and record them in `pkg.versions`. Only `pkg.keep` most
+/// recently installed versions are kept.
+pub fn keep(pkg: &mut Package, bin_dir: &Path) -> Result<()> {
+    let (sha256, size) = match (&pkg.sha256, pkg.size) {
+        (Some(sha256), Some(size)) => (sha256.clone(), size),
+        _ => return Ok(()),
+    };
+
+    if pkg.layout == Layout::Copy {
+        let kept_dir = create_version_dir(&pkg.bin_name, &pkg.tag)?;
+        let bin_names = pkg.bins.iter().map(ExtraBin::bin_name);
+        for bin_name in std::iter::once(pkg.bin_name.as_str()).chain(bin_names) {
+            fs::copy(
+                util::bin_path(bin_dir, bin_name),
+                util::bin_path(&kept_dir, bin_name),
+            )
+            .with_context(|| format!("copying a binary into the store: {:?}", kept_dir))?;
+        }
+    }
+
+    let version = StoredVersion {
+        tag: pkg.tag.clone(),
+        timestamp: pkg.timestamp,
+        sha256,
+        size,
+        bins: pkg.bins.clone(),
+        side_files: pkg.side_files.clone(),
+    };
+    let keep = pkg.keep.unwrap_or(DEFAULT_KEEP) as usize;
+    for version in record(&mut pkg.versions, version, keep) {
+        let pruned_dir = version_dir(&pkg.bin_name, &version.tag)?;
+        remove_dir(&pruned_dir)
+            .with_context(|| format!("removing a kept version: {:?}", pruned_dir))?;
+    }
+
+    Ok(())
+}
+
+/// Record a just installed `version` as the most recent one of `versions`, keeping at most
+/// `keep` of them. Returns the versions pruned.
+fn record(
+    versions: &mut Vec<StoredVersion>,
+    version: StoredVersion,
+    keep: usize,
+) -> Vec<StoredVersion> {
+    // i.e. reinstalled after a rollback, it becomes the most recent one again
+    versions.retain(|kept| kept.tag != version.tag);
+    versions.push(version);
+
+    let excess = versions.len().saturating_sub(keep.max(1));
+    versions.drain(..excess).collect()
+}
+
+/// A kept version of `versions` to roll back to from a version `current_tag`: the one `to`,
+/// or the one installed right before the current one.
+pub fn rollback_target<'a>(
+    versions: &'a [StoredVersion],
+    current_tag: &str,
+    to: Option<&str>,
+) -> Option<&'a StoredVersion> {
+    match to {
+        Some(tag) => versions.iter().find(|version| version.tag == tag),
+        None => {
+            let current = versions
+                .iter()
+                .position(|version| version.tag == current_tag)
+                .unwrap_or(versions.len());
+            current.checked_sub(1).map(|i| &versions[i])
+        }
+    }
+}
+
+/// A dir of a version `tag` of a binary named `bin_name`, created if it doesn't exist.
+pub fn create_version_dir(bin_name: &str, tag: &str) -> Result<PathBuf> {
+    let version_dir = version_dir(bin_name, tag)?;
+    fs::create_dir_all(&version_dir)
+        .with_context(|| format!("creating a store dir: {:?}", version_dir))?;
+    Ok(version_dir)
+}
+
+/// A path of a kept binary named `bin_name` of a version `tag` of `pkg`.
+pub fn stored_path(pkg: &Package, tag: &str, bin_name: &str) -> Result<PathBuf> {
+    let version_dir = version_dir(&pkg.bin_name, tag)?;
+    let bin_path = util::bin_path(&version_dir, bin_name);
+    Ok(bin_path)
+}
+
+/// Remove all kept versions of a binary named `bin_name`.
+pub fn remove(bin_name: &str) -> Result<()> {
+    let pkg_dir = util::data_dir(PKGS_DIR)?.join(bin_name);
+    remove_dir(&pkg_dir).with_context(|| format!("removing kept versions: {:?}", pkg_dir))
+}
+
+fn remove_dir(dir: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        res => res,
+    }
+}
+
+fn version_dir(bin_name: &str, tag: &str) -> Result<PathBuf> {
+    let dir_name = tag_dir_name(tag)?;
+    Ok(util::data_dir(PKGS_DIR)?.join(bin_name).join(dir_name))
+}
+
+/// A store dir name of a version `tag`, refusing ones that would leave the store.
+fn tag_dir_name(tag: &str) -> Result<String> {
+    // tags may look like paths, i.e. `cli/v1.2.0`
+    let dir_name = tag.replace(['/', '\\'], "_");
+    let mut components = Path::new(&dir_name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(dir_name),
+        _ => Err(anyhow!("invalid release tag for a store dir: {:?}", tag)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(tag: &str) -> StoredVersion {
+        StoredVersion {
+            tag: tag.to_owned(),
+            timestamp: Utc::now(),
+            sha256: String::new(),
+            size: 0,
+            bins: Vec::new(),
+            side_files: Vec::new(),
+        }
+    }
+
+    fn tags(versions: &[StoredVersion]) -> Vec<&str> {
+        versions
+            .iter()
+            .map(|version| version.tag.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn record_prunes_beyond_keep() {
+        let mut versions = vec![version("v1"), version("v2"), version("v3")];
+
+        let pruned = record(&mut versions, version("v4"), 3);
+        assert_eq!(tags(&pruned), ["v1"]);
+        assert_eq!(tags(&versions), ["v2", "v3", "v4"]);
+
+        // i.e. `--keep` lowered since
+        let pruned = record(&mut versions, version("v5"), 2);
+        assert_eq!(tags(&pruned), ["v2", "v3"]);
+        assert_eq!(tags(&versions), ["v4", "v5"]);
+
+        // reinstalled after a rollback
+        let pruned = record(&mut versions, version("v4"), 2);
+        assert!(pruned.is_empty());
+        assert_eq!(tags(&versions), ["v5", "v4"]);
+
+        let pruned = record(&mut versions, version("v6"), 0);
+        assert_eq!(tags(&pruned), ["v5", "v4"]);
+        assert_eq!(tags(&versions), ["v6"]);
+    }
+
+    #[test]
+    fn rollback_targets() {
+        let versions = [version("v1"), version("v2"), version("v3")];
+        let target = |current, to| rollback_target(&versions, current, to).map(|v| &v.tag);
+
+        assert_eq!(target("v3", None).unwrap(), "v2");
+        // i.e. rolled back to it before
+        assert_eq!(target("v2", None).unwrap(), "v1");
+        assert_eq!(target("v1", None), None);
+        // i.e. installed before versions were kept
+        assert_eq!(target("v4", None).unwrap(), "v3");
+        assert_eq!(target("v3", Some("v1")).unwrap(), "v1");
+        assert_eq!(target("v3", Some("v0")), None);
+        assert_eq!(rollback_target(&[], "v1", None), None);
+    }
+
+    #[test]
+    fn tag_dir_names() {
+        assert_eq!(tag_dir_name("v1.2.0").unwrap(), "v1.2.0");
+        assert_eq!(tag_dir_name("cli/v1.2.0").unwrap(), "cli_v1.2.0");
+        assert_eq!(tag_dir_name("..v1").unwrap(), "..v1");
+        assert!(tag_dir_name("..").is_err());
+        assert!(tag_dir_name(".").is_err());
+        assert!(tag_dir_name("").is_err());
+    }
+}
@@ -1,9 +1,11 @@
 use anyhow::{Context, Result};
-use std::{fs, path::Path};
+use std::{fs, io, path::Path};
 
 pub fn uninstall(bin_name: &str, bin_dir: &Path) -> Result<()> {
     let file_to_delete = bin_dir.join(bin_name);
-    fs::remove_file(&file_to_delete)
-        .context(format!("deleting a binary: {}", file_to_delete.display()))?;
-    Ok(())
+    match fs::remove_file(&file_to_delete) {
+        // i.e. deleted by hand, so that the package can still be uninstalled
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        res => res.context(format!("deleting a binary: {}", file_to_delete.display())),
+    }
 }
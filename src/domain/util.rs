@@ -1,4 +1,8 @@
-use std::{collections::HashSet, fs, path::PathBuf};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{anyhow, Context, Result};
 use console::style;
@@ -7,11 +11,11 @@ use indicatif::{ProgressBar, ProgressStyle};
 use lazy_static::lazy_static;
 use regex::Regex;
 
+use super::forge::ForgeKind;
+
 include!(concat!(env!("OUT_DIR"), "/generated.rs"));
 
 lazy_static! {
-    pub static ref RX_REPO: Regex = Regex::new(r"(?:https://github\.com/)?((?:[^/]+/[^/]+)|[^/]+)(?:/)?").expect("error parsing regex");
-
     pub static ref SEMVER: Regex = Regex::new(r"(?P<major>0|[1-9]\d*)\.(?P<minor>0|[1-9]\d*)\.(?P<patch>0|[1-9]\d*)(?:-(?P<prerelease>(?:0|[1-9]\d*|\d*[a-zA-Z-][0-9a-zA-Z-]*)(?:\.(?:0|[1-9]\d*|\d*[a-zA-Z-][0-9a-zA-Z-]*))*))?(?:\+(?P<buildmetadata>[0-9a-zA-Z-]+(?:\.[0-9a-zA-Z-]+)*))?").expect("error parsing regex");
 
     static ref TERMS: Regex =
@@ -25,6 +29,16 @@ pub fn message_fail(pb: &ProgressBar, repo_name: &str, msg: &str) {
     pb.finish_with_message(msg);
 }
 
+/// Print a warning above `pb`, or to stderr if `pb` isn't drawn.
+pub fn message_warn(pb: &ProgressBar, msg: &str) {
+    let msg = format!("{} {}", style("warning:").yellow(), msg);
+    if pb.is_hidden() {
+        eprintln!("{}", msg);
+    } else {
+        pb.println(msg);
+    }
+}
+
 pub fn matches_target(str: &str) -> bool {
     // `str` must not have any terms present in `EXCLUDE_SET`
     for term in TERMS.find_iter(&str.to_lowercase()) {
@@ -58,22 +72,65 @@ pub fn matches_semver(tag_name: &str, semver: &str) -> bool {
     false
 }
 
+/// Whether a page of release `tag_names` (listed newest first) sorts entirely below
+/// the lowest version matching `semver`, so that no older release could match it.
+/// Tags that aren't semvers, such as nightlies, are not taken into account.
+pub fn below_semver<'a>(tag_names: impl Iterator<Item = &'a str>, semver: &str) -> bool {
+    let floor = match semver::VersionReq::parse(semver)
+        .ok()
+        .and_then(|ver_req| semver_floor(&ver_req))
+    {
+        Some(floor) => floor,
+        None => return false,
+    };
+
+    let mut versions = tag_names
+        .filter_map(|tag_name| SEMVER.find(tag_name))
+        .filter_map(|ver| semver::Version::parse(ver.as_str()).ok())
+        .peekable();
+
+    versions.peek().is_some() && versions.all(|ver| ver < floor)
+}
+
+/// The lowest version, that may match `ver_req`, if it is bounded from below.
+fn semver_floor(ver_req: &semver::VersionReq) -> Option<semver::Version> {
+    use semver::Op;
+
+    ver_req
+        .comparators
+        .iter()
+        .filter(|cmp| !matches!(cmp.op, Op::Less | Op::LessEq))
+        .map(|cmp| semver::Version {
+            major: cmp.major,
+            minor: cmp.minor.unwrap_or(0),
+            patch: cmp.patch.unwrap_or(0),
+            pre: cmp.pre.clone(),
+            build: semver::BuildMetadata::EMPTY,
+        })
+        .max()
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ArchiveKind {
     BZip,
     GZip,
     XZ,
+    Zstd,
+    Lz4,
     Zip,
+    SevenZip,
     Tar(TarKind),
     Uncompressed,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TarKind {
     Uncompressed,
     BZip,
     GZip,
     XZ,
+    Zstd,
+    Lz4,
 }
 
 pub fn archive_kind(str: &str) -> ArchiveKind {
@@ -82,12 +139,18 @@ pub fn archive_kind(str: &str) -> ArchiveKind {
         ArchiveKind::Uncompressed
     } else if str.ends_with(".zip") {
         ArchiveKind::Zip
+    } else if str.ends_with(".7z") {
+        ArchiveKind::SevenZip
     } else if str.ends_with(".tar.gz") || str.ends_with(".tgz") {
         ArchiveKind::Tar(TarKind::GZip)
     } else if str.ends_with(".tar.bz2") || str.ends_with(".tbz") {
         ArchiveKind::Tar(TarKind::BZip)
     } else if str.ends_with(".tar.xz") || str.ends_with(".txz") {
         ArchiveKind::Tar(TarKind::XZ)
+    } else if str.ends_with(".tar.zst") || str.ends_with(".tzst") {
+        ArchiveKind::Tar(TarKind::Zstd)
+    } else if str.ends_with(".tar.lz4") {
+        ArchiveKind::Tar(TarKind::Lz4)
     } else if str.ends_with(".tar") {
         ArchiveKind::Tar(TarKind::Uncompressed)
     } else if str.ends_with(".gz") || str.ends_with(".gzip") || str.ends_with(".gnuzip") {
@@ -96,6 +159,10 @@ pub fn archive_kind(str: &str) -> ArchiveKind {
         ArchiveKind::BZip
     } else if str.ends_with(".xz") {
         ArchiveKind::XZ
+    } else if str.ends_with(".zst") {
+        ArchiveKind::Zstd
+    } else if str.ends_with(".lz4") {
+        ArchiveKind::Lz4
     } else {
         // NB: some names contain '.' in them, that are not necessary the archive extensions.
         // example: https://github.com/cloudfoundry/bosh-bootloader/releases/tag/v8.4.83
@@ -106,6 +173,14 @@ pub fn archive_kind(str: &str) -> ArchiveKind {
     }
 }
 
+/// Run a blocking (CPU or file system bound) `f` on a thread of its own, so that it doesn't
+/// hold up other tasks of the current thread runtime, i.e. concurrent `update` jobs.
+pub async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
 /// Checks to see if `~/.config/gitrel/` directory exists, and then returns
 /// the `~/.config/gitrel/packages.json` PathBuf.
 pub fn packages_file() -> Result<PathBuf> {
@@ -121,6 +196,32 @@ pub fn packages_file() -> Result<PathBuf> {
     Ok(path)
 }
 
+/// Returns the `~/.cache/gitrel/<sub_dir>` PathBuf, creating the directory if it
+/// doesn't exist. Like the config dir, it's used regardless of an operating system kind.
+pub fn cache_dir(sub_dir: &str) -> Result<PathBuf> {
+    let base_dirs = BaseDirs::new().ok_or_else(|| anyhow!("unable to get usable `base dir`"))?;
+    let home_dir = base_dirs.home_dir();
+
+    let cache_dir = home_dir.join(".cache/gitrel/").join(sub_dir);
+    fs::create_dir_all(cache_dir.as_path())
+        .with_context(|| format!("unable to create cache dir: {:?}", cache_dir.as_path()))?;
+
+    Ok(cache_dir)
+}
+
+/// Returns the `~/.local/share/gitrel/<sub_dir>` PathBuf, creating the directory if it
+/// doesn't exist. Like the cache dir, it's used regardless of an operating system kind.
+pub fn data_dir(sub_dir: &str) -> Result<PathBuf> {
+    let base_dirs = BaseDirs::new().ok_or_else(|| anyhow!("unable to get usable `base dir`"))?;
+    let home_dir = base_dirs.home_dir();
+
+    let data_dir = home_dir.join(".local/share/gitrel/").join(sub_dir);
+    fs::create_dir_all(data_dir.as_path())
+        .with_context(|| format!("unable to create data dir: {:?}", data_dir.as_path()))?;
+
+    Ok(data_dir)
+}
+
 pub fn bin_dir() -> Result<PathBuf> {
     let base_dirs = BaseDirs::new().ok_or_else(|| anyhow!("unable to get usable `base dir`"))?;
     let home_dir = base_dirs.home_dir();
@@ -141,6 +242,15 @@ pub fn bin_dir() -> Result<PathBuf> {
     Ok(bin_dir)
 }
 
+/// Path of a binary named `bin_name` installed into `bin_dir`.
+pub fn bin_path(bin_dir: &Path, bin_name: &str) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        bin_dir.join(format!("{}.exe", bin_name))
+    } else {
+        bin_dir.join(bin_name)
+    }
+}
+
 pub fn bin_dir_display() -> Result<&'static str> {
     let base_dirs = BaseDirs::new().ok_or_else(|| anyhow!("unable to get usable `base dir`"))?;
     let home_dir = base_dirs.home_dir();
@@ -154,8 +264,59 @@ pub fn bin_dir_display() -> Result<&'static str> {
     }
 }
 
-/// Returns a tuple (user, repo, requested_version)
-pub fn parse_gh_repo_spec(repo_spec: &str) -> Result<(String, String, String)> {
+/// A parsed `[https://host/]user/repo[@version]` package spec.
+#[derive(Debug, PartialEq)]
+pub struct RepoSpec {
+    /// forge host, when specified
+    pub host: Option<String>,
+    pub user: String,
+    pub repo: String,
+    pub requested: String,
+}
+
+impl RepoSpec {
+    /// Resolves a forge kind and an API root for this spec. Explicitly provided
+    /// `forge` takes precedence over what is derived from the host, and so does
+    /// `api_url`, but only for GitHub, or a spec without a host.
+    pub fn resolve_forge(
+        &self,
+        forge: Option<ForgeKind>,
+        api_url: Option<&str>,
+    ) -> Result<(ForgeKind, Option<String>)> {
+        let forge = match (forge, &self.host) {
+            (Some(forge), _) => forge,
+            (None, Some(host)) => ForgeKind::from_host(host).ok_or_else(|| {
+                anyhow!(
+                    "unknown forge host `{}`, use `--forge` to specify its kind",
+                    host
+                )
+            })?,
+            (None, None) => ForgeKind::default(),
+        };
+
+        if forge == ForgeKind::GitHub && self.user.contains('/') {
+            return Err(anyhow!(
+                "parsing repo name: {}/{}, GitHub repos are specified as `user/repo`",
+                self.user,
+                self.repo
+            ));
+        }
+
+        // i.e. a GitHub Enterprise root exported, while installing from gitlab.com
+        let api_url = match (api_url, &self.host) {
+            (Some(api_url), None) => Some(api_url.to_owned()),
+            (Some(api_url), Some(_)) if forge == ForgeKind::GitHub => Some(api_url.to_owned()),
+            (_, Some(host)) => forge.api_url_for_host(host),
+            (_, None) => None,
+        };
+
+        Ok((forge, api_url))
+    }
+}
+
+/// Parses `[https://host/]user/repo[@version]`, where `host` is optional for GitHub.
+/// Specs of other forges may include subgroups, i.e. `gitlab.com/group/subgroup/repo`.
+pub fn parse_gh_repo_spec(repo_spec: &str) -> Result<RepoSpec> {
     // split [https://github.com/]user/repo@version at '@'
     let (repo_spec, requested) = if repo_spec.contains('@') {
         let (repo, requested) = repo_spec.split_at(repo_spec.find('@').unwrap());
@@ -164,16 +325,138 @@ pub fn parse_gh_repo_spec(repo_spec: &str) -> Result<(String, String, String)> {
         (repo_spec, "*")
     };
 
-    // capture the "user/repo" or the "repo"
-    let caps = RX_REPO.captures(repo_spec).context("parsing repo name")?;
-    let cap = caps.get(1).context("capturing repo name")?.as_str();
+    let repo_spec = repo_spec
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_matches('/');
 
-    // make sure we got the ("user/repo", "repo") tuple
-    let (user, repo) = if cap.contains('/') {
-        cap.split_once('/').unwrap()
-    } else {
-        (cap, cap)
+    // user names can't contain dots, so a leading segment with one is a host
+    let (host, path) = match repo_spec.split_once('/') {
+        Some((host, path)) if host.contains('.') => (Some(host), path),
+        _ => (None, repo_spec),
     };
 
-    Ok((user.to_owned(), repo.to_owned(), requested.to_owned()))
+    // make sure we got the ("user", "repo") tuple, where a "user" of forges other than
+    // GitHub may be a group with subgroups, as checked once a forge is resolved
+    let (user, repo) = path.rsplit_once('/').unwrap_or((path, path));
+
+    if user.is_empty() || repo.is_empty() {
+        return Err(anyhow!("parsing repo name: {}", repo_spec));
+    }
+
+    Ok(RepoSpec {
+        host: host.map(str::to_owned),
+        user: user.to_owned(),
+        repo: repo.to_owned(),
+        requested: requested.to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_repo_spec() {
+        let spec = parse_gh_repo_spec("rust-analyzer@nightly").unwrap();
+        assert_eq!(spec.host, None);
+        assert_eq!(
+            (spec.user.as_str(), spec.repo.as_str()),
+            ("rust-analyzer", "rust-analyzer")
+        );
+        assert_eq!(spec.requested, "nightly");
+
+        let spec = parse_gh_repo_spec("https://github.com/JohnnyMorganz/StyLua@^0.11").unwrap();
+        assert_eq!(spec.host.as_deref(), Some("github.com"));
+        assert_eq!(
+            (spec.user.as_str(), spec.repo.as_str()),
+            ("JohnnyMorganz", "StyLua")
+        );
+        assert_eq!(spec.requested, "^0.11");
+
+        let spec = parse_gh_repo_spec("gitlab.com/gitlab-org/cli/glab").unwrap();
+        assert_eq!(spec.host.as_deref(), Some("gitlab.com"));
+        assert_eq!(
+            (spec.user.as_str(), spec.repo.as_str()),
+            ("gitlab-org/cli", "glab")
+        );
+        assert_eq!(spec.requested, "*");
+    }
+
+    #[test]
+    fn below_semver_floor() {
+        let tags = ["nightly", "v1.1.9", "v1.0.0"];
+        assert!(below_semver(tags.into_iter(), "^1.2"));
+        assert!(!below_semver(tags.into_iter(), "~1.1"));
+        assert!(!below_semver(tags.into_iter(), "<1.2"));
+        assert!(!below_semver(["nightly"].into_iter(), "^1.2"));
+        assert!(below_semver(["2.9.0"].into_iter(), ">=3, <4"));
+    }
+
+    #[test]
+    fn archive_kinds() {
+        assert_eq!(
+            archive_kind("tool-x86_64-linux.tar.zst"),
+            ArchiveKind::Tar(TarKind::Zstd)
+        );
+        assert_eq!(archive_kind("tool.tzst"), ArchiveKind::Tar(TarKind::Zstd));
+        assert_eq!(archive_kind("tool.tar.lz4"), ArchiveKind::Tar(TarKind::Lz4));
+        assert_eq!(archive_kind("tool-linux.zst"), ArchiveKind::Zstd);
+        assert_eq!(archive_kind("tool-linux.lz4"), ArchiveKind::Lz4);
+        assert_eq!(archive_kind("tool_windows.7z"), ArchiveKind::SevenZip);
+        assert_eq!(archive_kind("bbl-v8.4.83-osx"), ArchiveKind::Uncompressed);
+    }
+
+    #[test]
+    fn resolve_forge() {
+        let spec = parse_gh_repo_spec("codeberg.org/forgejo/forgejo").unwrap();
+        assert_eq!(
+            spec.resolve_forge(None, None).unwrap(),
+            (ForgeKind::Gitea, None)
+        );
+
+        let spec = parse_gh_repo_spec("https://git.example.com/tools/mytool@^1").unwrap();
+        assert!(spec.resolve_forge(None, None).is_err());
+        assert_eq!(
+            spec.resolve_forge(Some(ForgeKind::Gitea), None).unwrap(),
+            (
+                ForgeKind::Gitea,
+                Some("https://git.example.com/api/v1".to_owned())
+            )
+        );
+
+        let spec = parse_gh_repo_spec("group/subgroup/repo").unwrap();
+        assert!(spec.resolve_forge(None, None).is_err());
+        assert_eq!(
+            spec.resolve_forge(Some(ForgeKind::GitLab), None).unwrap(),
+            (ForgeKind::GitLab, None)
+        );
+        assert_eq!(
+            (spec.user.as_str(), spec.repo.as_str()),
+            ("group/subgroup", "repo")
+        );
+        let spec = parse_gh_repo_spec("github.com/user/repo/releases").unwrap();
+        assert!(spec.resolve_forge(None, None).is_err());
+
+        let ghe_url = Some("https://ghe.example.com/api/v3");
+        let spec = parse_gh_repo_spec("gitlab.com/gitlab-org/cli/glab").unwrap();
+        assert_eq!(
+            spec.resolve_forge(None, ghe_url).unwrap(),
+            (ForgeKind::GitLab, None)
+        );
+        let spec = parse_gh_repo_spec("git.example.com/tools/mytool").unwrap();
+        assert_eq!(
+            spec.resolve_forge(Some(ForgeKind::GitLab), ghe_url)
+                .unwrap(),
+            (
+                ForgeKind::GitLab,
+                Some("https://git.example.com/api/v4".to_owned())
+            )
+        );
+        let spec = parse_gh_repo_spec("github.com/tools/mytool").unwrap();
+        assert_eq!(
+            spec.resolve_forge(None, ghe_url).unwrap(),
+            (ForgeKind::GitHub, ghe_url.map(str::to_owned))
+        );
+    }
 }
@@ -8,28 +8,35 @@ use anyhow::Result;
 use clap::Parser;
 
 use crate::cli::Cli;
+use crate::domain::error::{self, ExitCode};
 
 fn main() {
-    std::process::exit(match run_app() {
+    let args = Cli::parse();
+    let quiet = args.quiet;
+
+    std::process::exit(match run_app(args) {
         Ok(()) => 0,
         Err(e) => {
-            if cfg!(debug_assertions) {
-                eprint!("{}\n\n", e);
+            let code = error::exit_code(&e);
+            if !quiet && code != ExitCode::UpToDate {
+                // `{:?}` displays a whole chain of causes
+                eprint!("\nerror: {:?}\n\n", e);
             }
-            1
+            code as i32
         }
     });
 }
 
-fn run_app() -> Result<()> {
-    let args = Cli::parse();
-
+fn run_app(args: Cli) -> Result<()> {
     match args.command {
-        cli::Commands::Install(args) => rt_current_thread(cmd::install(args)),
+        cli::Commands::Install(args) => rt_current_thread(cmd::install(*args)),
         cli::Commands::Update(args) => rt_current_thread(cmd::update(args)),
         cli::Commands::Uninstall(args) => rt_current_thread(cmd::uninstall(args)),
         cli::Commands::List(args) => cmd::list(args),
+        cli::Commands::Verify(args) => cmd::verify(args),
+        cli::Commands::Rollback(args) => cmd::rollback(args),
         cli::Commands::Info(args) => rt_current_thread(cmd::info(args)),
+        cli::Commands::Cache(command) => cmd::cache(command),
     }
 }
 